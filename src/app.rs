@@ -0,0 +1,1791 @@
+use std::collections::HashMap;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use colored::Colorize;
+use rand::Rng;
+use rand::seq::SliceRandom;
+use tracing::{debug, info, instrument};
+
+use crate::cli::{Args, Command, ConfigCommand, FieldFile, Retag};
+use crate::codeowners::CodeOwners;
+use crate::config::{self, Config, TagsScope};
+use crate::error::{Error, Result};
+use crate::git::{self, BaseCandidates, BranchInfo};
+use crate::forge::{self, Forge, NewPr, PrUpdate};
+use crate::github::{self, Reviewer};
+use crate::pr::PullRequest;
+use crate::tags::tags::{self, Tags};
+use crate::template;
+use crate::ui::{self, Prompter};
+
+/// Options of [`create_pr`].
+#[derive(Debug, Default, Clone)]
+pub struct CreateOptions {
+    /// `owner/name` the PR is opened against, inferred by `gh` when missing.
+    pub repo: Option<String>,
+    /// Refresh the related PRs section of PRs sharing the tag.
+    pub update_related: bool,
+    /// Don't run `gh`, the would-be commands are returned instead.
+    pub dry_run: bool,
+    /// Leave the Jira tracking line out of the body.
+    pub no_jira_link: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct CreatedPr {
+    /// Url of the created PR, or the `gh` command in dry run.
+    pub url: String,
+    pub related: Vec<RelatedUpdate>,
+}
+
+#[derive(Debug, Clone)]
+pub struct RelatedUpdate {
+    pub number: u32,
+    pub outcome: UpdateOutcome,
+}
+
+#[derive(Debug, Clone)]
+pub enum UpdateOutcome {
+    Unchanged,
+    Updated(String),
+    Failed(String),
+}
+
+/// Publishes `request` and updates its related PRs, without prompting or printing.
+pub fn create_pr(config: &Config, request: &PullRequest, opts: CreateOptions) -> Result<CreatedPr> {
+    create(forge::client(config, opts.dry_run, None)?.as_ref(), config, request, &opts)
+}
+
+#[instrument(skip_all, fields(tag = %request.tag, base = %request.base))]
+fn create(github: &dyn Forge, config: &Config, request: &PullRequest, opts: &CreateOptions) -> Result<CreatedPr> {
+    let url = publish_pr(github, config, opts.repo.as_deref(), request, PublishOptions { no_jira_link: opts.no_jira_link, ..Default::default() })?;
+    info!(%url, "published");
+
+    let related = if opts.update_related {
+        update_related_prs(github, config, &request.tag, &RelatedScope { repo: opts.repo.as_deref(), ..Default::default() })?
+    } else {
+        Vec::new()
+    };
+
+    Ok(CreatedPr { url, related })
+}
+
+/// Interactive flow of the `git-pr` binary.
+pub fn run(args: &Args, prompter: &dyn Prompter) -> Result<()> {
+    // Printed without loading the config, it's needed the most while the config is broken.
+    if let Some(Command::Config { command: ConfigCommand::Schema }) = &args.command {
+        print!("{}", Config::schema());
+        return Ok(());
+    }
+    if args.print_config_path {
+        print_config_paths(args);
+        return Ok(());
+    }
+    let mut config = Config::load(args.config_file.as_deref())?;
+    if let Some(Command::Config { command }) = &args.command {
+        let path = match &args.config_file {
+            Some(file) => file.display().to_string(),
+            None => config::get_config_path(),
+        };
+        return run_config_command(&config, &path, command);
+    }
+    if let Some(count) = args.reviewer_team_size {
+        match config.github.auto_reviewers.as_mut() {
+            Some(auto) => auto.count = count,
+            None => return Err(Error::InvalidInput("--reviewer-team-size requires github.auto_reviewers.pool".into())),
+        }
+    }
+
+    // Without --template the profile is picked once the tag is known.
+    if let Some(name) = &args.template {
+        select_template(&mut config, name)?;
+    }
+    if args.draft {
+        config.github.draft = true;
+    }
+    if let Some(milestone) = &args.milestone {
+        config.github.default_milestone = Some(milestone.clone());
+    }
+    if args.reviewers_from_codeowners {
+        config.github.reviewers_from_codeowners = true;
+    }
+
+    let client = forge::client(&config, args.dry_run, args.out.clone())?;
+    let github = client.as_ref();
+    debug!(?args, "starting");
+
+    if let Some(Command::Edit { number }) = &args.command {
+        if args.template.is_none() {
+            select_template(&mut config, config::DEFAULT_TEMPLATE)?;
+        }
+        return edit_pr(github, &config, prompter, target_repo(args, &config).as_deref(), *number);
+    }
+    if let Some(Command::Reconcile { tag }) = &args.command {
+        // Outside of a repository and without --repo every PR with the tag is reconciled.
+        let repo = target_repo(args, &config);
+        let scope = related_scope(args, repo.as_deref());
+        print_reconciled_prs(tag, &reconcile_related_prs(github, &config, tag, &scope)?);
+        return Ok(());
+    }
+
+    let cache = (config.git.cache && !args.no_cache).then(config::get_scan_cache_path);
+    let branch_info = git::get_branch_bases_and_commits(&config.git, cache.as_deref().map(Path::new))?;
+    if branch_info.commits.is_empty() {
+        return Err(Error::NoCommits);
+    }
+    let repo = target_repo(args, &config);
+    if !args.update_only {
+        if let Some(existing) = github.get_pr_by_branch(repo.as_deref(), &branch_info.branch)? {
+            return offer_existing_pr_update(github, &config, prompter, &branch_info.branch, &existing);
+        }
+    }
+
+    let tags_path = config::get_tags_path(config.tags.scope, git::common_dir(&git::get_repository()?));
+    let mut tags = Tags::from_file(tags_path)?.with_ranking(config.tags.ranking);
+    debug!(count = tags.len(), "tag history loaded");
+    let tag = args.tag.as_ref().or(args.retag.as_ref().map(|retag| &retag.to));
+    let mut pr = build_pr_from_branch(&mut config, prompter, &branch_info, &mut tags, tag.map(String::as_str), args.template.is_none())?;
+    let pinned_base = args.base.as_ref().or(config.github.default_base.as_ref());
+    pr.base = match pinned_base {
+        Some(base) => validate_base_branch(&config, base)?,
+        None => select_base_branch(prompter, &branch_info, git::branch_names(&git::get_repository()?, &config.github.remote)?)?,
+    };
+
+    if !args.update_only {
+        if pinned_base.is_none() {
+            ensure_base_on_remote(&config, prompter, &mut pr)?;
+        }
+        ensure_base_differs(&pr.base, &branch_info.branch)?;
+        if args.field_files.iter().map(|f| &f.path).chain(&args.append_body).filter(|path| path.as_os_str() == "-").count() > 1 {
+            return Err(Error::InvalidInput("stdin (-) can be read by a single --field-file or --append-body".into()));
+        }
+        let fields = read_field_files(&config, &args.field_files)?;
+        if let Some(path) = &args.append_body {
+            pr.appendix = Some(read_input(path)?);
+        }
+        pr.closes = args.closes.clone();
+
+        let drafts = PathBuf::from(config::get_drafts_dir(git::common_dir(&git::get_repository()?)));
+        resume_draft(prompter, &drafts, &mut pr)?;
+        if pr.reviewers.is_empty() && config.github.reviewers_from_codeowners && !args.no_reviewers {
+            pr.reviewers = codeowners_reviewers(&config, &pr.base)?;
+        }
+
+        run_pre_create_hook(&config, &pr)?;
+        // Reviewers of the compose page are picked in the browser.
+        let answers = Answers { reviewers: &args.reviewers, no_reviewers: args.no_reviewers || args.web, fields };
+        gather_pr_details(github, &config, prompter, &mut pr, repo.as_deref(), &answers, Some(&drafts))?;
+
+        let opts = PublishOptions { no_jira_link: args.no_jira_link, web: args.web };
+        let url = match publish_pr(github, &config, repo.as_deref(), &pr, opts) {
+            Ok(url) => url,
+            Err(err) => {
+                let draft = PullRequest::draft_path(&drafts, &pr.branch);
+                println!("{} Your answers are saved in {}, rerun to resume them", "!".bright_yellow(), draft.display());
+                return Err(err);
+            }
+        };
+        if args.web && !args.dry_run {
+            // The PR doesn't exist until it's submitted on the page, nothing to relate yet,
+            // and the draft is kept in case the page is closed.
+            println!("{} Finish the PR in the browser, rerun with -u to update related PRs", "+".bright_green());
+            return Ok(());
+        }
+        if !args.dry_run {
+            PullRequest::delete_draft(&drafts, &pr.branch)?;
+        }
+        if args.dry_run {
+            println!("Dry run: {}", url);
+        } else {
+            println!("Published at: {}", url);
+        }
+
+        if !args.dry_run && (args.open || config.github.open_after_create) {
+            if let Err(err) = github.open_in_browser(&url) {
+                println!("{} Unable to open {}: {}", "!".bright_yellow(), url, err);
+            }
+        }
+        if !args.dry_run && (args.copy || config.github.copy_url) {
+            ui::copy_to_clipboard(&url);
+        }
+        if !args.no_related {
+            let scope = related_scope(args, repo.as_deref());
+            print_related_updates(&update_related_prs(github, &config, &pr.tag, &scope)?);
+        }
+
+        return Ok(());
+    }
+
+    let scope = related_scope(args, repo.as_deref());
+    if let Some(retag) = &args.retag {
+        print_retitled_prs(retag, &retag_prs(github, &config, retag, &scope)?);
+    }
+    print_related_updates(&update_related_prs(github, &config, &pr.tag, &scope)?);
+
+    Ok(())
+}
+
+/// `owner/name` given with `--repo`, or of the `github.remote` of the current repository.
+fn target_repo(args: &Args, config: &Config) -> Option<String> {
+    args.repo.clone()
+        .or_else(|| git::get_repository().ok().and_then(|repo| git::get_remote_repo(&repo, &config.github.remote)))
+}
+
+fn related_scope<'a>(args: &Args, repo: Option<&'a str>) -> RelatedScope<'a> {
+    RelatedScope {
+        repo,
+        updated_since: args.since.map(|days| days_ago(SystemTime::now(), days)),
+    }
+}
+
+/// Makes the `name` profile the template of `config`.
+fn select_template(config: &mut Config, name: &str) -> Result<()> {
+    config.select_template(name).map_err(Error::InvalidInput)?;
+    warn_unknown_placeholders(config);
+
+    Ok(())
+}
+
+fn warn_unknown_placeholders(config: &Config) {
+    let unknown = config.template.unknown_placeholders();
+    if !unknown.is_empty() {
+        println!("{} template.body references unknown fields: {}", "!".bright_yellow(), unknown.join(", ").bright_cyan());
+    }
+}
+
+/// Paths of `--print-config-path`, they are printed even with a broken config or outside of a repository.
+fn print_config_paths(args: &Args) {
+    let path = match &args.config_file {
+        Some(file) => file.display().to_string(),
+        None => config::get_config_path(),
+    };
+    let found = if Path::new(&path).is_file() { "found" } else { "not found" };
+    ui::print_value("Config dir", &config::get_config_dir());
+    ui::print_value("Config file", &format!("{} ({})", path, found));
+
+    let scope = Config::load(args.config_file.as_deref()).map(|config| config.tags.scope).unwrap_or_default();
+    let tags_path = match (scope, git::get_repository()) {
+        (TagsScope::Repo, Err(_)) => "git-pr-tags.txt of the .git directory, not in a repository".to_string(),
+        (_, repo) => {
+            let git_dir = repo.as_ref().map(git::common_dir).unwrap_or(Path::new(""));
+            config::get_tags_path(scope, git_dir)
+        }
+    };
+    ui::print_value("Tags file", &tags_path);
+}
+
+/// `path` is the file `config` was loaded from.
+fn run_config_command(config: &Config, path: &str, command: &ConfigCommand) -> Result<()> {
+    match command {
+        ConfigCommand::Validate => {
+            config.validate()?;
+            println!("{} {} is valid", "+".bright_green(), path.bright_cyan());
+        }
+        ConfigCommand::Show => {
+            println!("# {}", path);
+            for (var, key) in config::env_overrides() {
+                println!("# {} is set by ${}", key, var);
+            }
+            print!("{}", config.to_yaml()?);
+        }
+        ConfigCommand::Schema => print!("{}", Config::schema()),
+    }
+
+    Ok(())
+}
+
+/// `tag` pins the tag of the PR instead of detecting it from the commits.
+/// With `profile_by_tag` the profile of `template.profile_rules` is selected before formatting the title.
+fn build_pr_from_branch(config: &mut Config, prompter: &dyn Prompter, branch_info: &BranchInfo, tags: &mut Tags, tag: Option<&str>, profile_by_tag: bool) -> Result<PullRequest> {
+    let mut pr = PullRequest {
+        branch: branch_info.branch.clone(),
+        commits: branch_info.messages.clone(),
+        ..Default::default()
+    };
+
+    if let Some(tag) = tag {
+        config.tags.validate(tag).map_err(Error::InvalidInput)?;
+    }
+    let detected = match tag {
+        Some(_) => None,
+        None => tags::extract_from_vec(branch_info.commits.clone()),
+    };
+
+    let from_commit = detected.is_some();
+    let (tag, title) = match detected {
+        Some((tag, commit)) => {
+            tags.add_and_save(tag.clone())?;
+            (tag, commit)
+        }
+        None => {
+            let title = prompter.title(&branch_info.commits)?;
+            let tag = match tag {
+                Some(tag) => {
+                    ui::print_value("PR Tag", tag);
+                    tag.to_string()
+                }
+                None => prompter.tag(&config.tags, &tags.ranked())?,
+            };
+
+            tags.add(tag.clone());
+            tags.save()?;
+            (tag, title)
+        }
+    };
+
+    if profile_by_tag {
+        let profile = config.template.profile_for_tag(&tag).unwrap_or(config::DEFAULT_TEMPLATE).to_string();
+        select_template(config, &profile)?;
+    }
+    pr.title = template::format_title(config, &tag, &tags::strip_from_str(&title, &tag));
+    pr.tag = tag;
+    if from_commit {
+        ui::print_value("PR title", &pr.title);
+        ui::print_value("PR Tag", &pr.tag);
+    }
+    pr.is_jira = config.is_tracked_jira_ticket(&pr.tag);
+
+    Ok(pr)
+}
+
+/// Asks for the base only when more than one was detected, `known` are the other accepted branches.
+fn select_base_branch(prompter: &dyn Prompter, branch_info: &BranchInfo, known: Vec<String>) -> Result<String> {
+    if branch_info.bases.len() > 1 {
+        return prompter.base(BaseCandidates { detected: branch_info.bases.clone(), known });
+    }
+
+    let base = branch_info.bases.first()
+        .cloned()
+        .ok_or_else(|| Error::InvalidInput("unable to detect PR base".into()))?;
+    ui::print_value("PR base", &base);
+
+    Ok(base)
+}
+
+fn validate_base_branch(config: &Config, base: &str) -> Result<String> {
+    let repo = git::get_repository()?;
+    let remote = config.github.remote.as_str();
+    if !git::branch_exists(&repo, remote, base) {
+        return Err(Error::InvalidInput(format!("base branch {} does not exist locally or on {}", base, remote)));
+    }
+    ui::print_value("PR base", base);
+
+    Ok(base.to_string())
+}
+
+fn ensure_base_on_remote(config: &Config, prompter: &dyn Prompter, pr: &mut PullRequest) -> Result<()> {
+    let repo = git::get_repository()?;
+    let remote = config.github.remote.as_str();
+    if git::base_exists_on_remote(&repo, remote, &pr.base) {
+        return Ok(());
+    }
+
+    println!("{} Base {} does not exist on {}. Was it merged and deleted?", "!".bright_yellow(), pr.base.bright_cyan(), remote.bright_cyan());
+
+    let mut alternatives: Vec<String> = Vec::new();
+    if let Some(default_branch) = git::get_remote_default_branch(&repo, remote) {
+        alternatives.push(default_branch);
+    }
+    alternatives.push(pr.base.clone());
+
+    pr.base = prompter.base(BaseCandidates { detected: alternatives, known: git::branch_names(&repo, remote)? })?;
+
+    Ok(())
+}
+
+fn ensure_base_differs(base: &str, branch: &str) -> Result<()> {
+    if base == branch {
+        return Err(Error::InvalidInput(format!("PR base {} is the current branch, pick a different base with --base", base)));
+    }
+
+    Ok(())
+}
+
+/// Content of `path` given with a flag, `-` reads stdin.
+fn read_input(path: &Path) -> Result<String> {
+    if path == Path::new("-") {
+        let mut content = String::new();
+        std::io::stdin().read_to_string(&mut content)?;
+        return Ok(content);
+    }
+
+    Ok(std::fs::read_to_string(path)?)
+}
+
+/// Values of `--field-file` by field name, checked against the fields of the template.
+fn read_field_files(config: &Config, files: &[FieldFile]) -> Result<HashMap<String, String>> {
+    let mut values = HashMap::new();
+    for file in files {
+        let Some(field) = config.template.fields.iter().find(|field| field.name == file.name) else {
+            let names: Vec<&str> = config.template.fields.iter().map(|field| field.name.as_str()).collect();
+            return Err(Error::InvalidInput(format!("unknown field {} in --field-file, template fields: {}", file.name, names.join(", "))));
+        };
+        let value = read_input(&file.path)?;
+        field.validate(&value).map_err(Error::InvalidInput)?;
+        values.insert(file.name.clone(), value.trim_end().to_string());
+    }
+
+    Ok(values)
+}
+
+/// Answers given with flags, their prompts are skipped.
+#[derive(Debug, Default)]
+struct Answers<'a> {
+    /// Reviewers of `--reviewer`, merged with `github.default_reviewers`.
+    reviewers: &'a [String],
+    /// `--no-reviewers`, the PR is opened without reviewers.
+    no_reviewers: bool,
+    /// Field values by name, eg. of `--field-file`.
+    fields: HashMap<String, String>,
+}
+
+/// Offers to continue a draft saved for the branch, its answers pre-fill the prompts.
+#[instrument(skip_all)]
+fn resume_draft(prompter: &dyn Prompter, drafts: &Path, pr: &mut PullRequest) -> Result<()> {
+    let Some(draft) = PullRequest::load_draft(drafts, &pr.branch)? else {
+        return Ok(());
+    };
+
+    if prompter.confirm(format!("Resume the draft saved for {}?", pr.branch).as_str(), true)? {
+        pr.fields = draft.fields;
+        pr.reviewers = draft.reviewers;
+    }
+
+    Ok(())
+}
+
+/// Values already in `pr` are used as defaults, answers are saved as a draft in `drafts`.
+/// Fields and non-empty reviewers of `answers` are used instead of prompting.
+#[instrument(skip_all)]
+fn gather_pr_details(github: &dyn Forge, config: &Config, prompter: &dyn Prompter, pr: &mut PullRequest, repo: Option<&str>, answers: &Answers, drafts: Option<&Path>) -> Result<()> {
+    let placeholders = pr.placeholders();
+    for field in &config.template.fields {
+        if let Some(value) = answers.fields.get(&field.name) {
+            pr.fields.insert(field.name.clone(), value.clone());
+            continue;
+        }
+        let mut field = field.clone();
+        if field.name == "description" && field.default.is_none() {
+            field.default = pr.single_commit_body();
+        }
+        if let Some(value) = pr.fields.get(&field.name) {
+            field.default = Some(value.clone());
+        }
+        let value = prompter.field(&config.template, &field, &placeholders)?;
+        pr.fields.insert(field.name.clone(), value);
+
+        if let Some(drafts) = drafts {
+            pr.save_draft(drafts)?;
+        }
+    }
+    // Neither the author nor the assignable users are looked up without reviewers.
+    pr.reviewers = if answers.no_reviewers {
+        Vec::new()
+    } else {
+        gather_reviewers(github, config, prompter, pr, repo, answers.reviewers)?
+    };
+
+    // Kept until the PR is published, so a failed publish can be resumed.
+    if let Some(drafts) = drafts {
+        pr.save_draft(drafts)?;
+    }
+
+    Ok(())
+}
+
+/// Reviewers of `requested`, picked from `github.auto_reviewers` or prompted for, `pr.reviewers` are pre-selected.
+fn gather_reviewers(github: &dyn Forge, config: &Config, prompter: &dyn Prompter, pr: &PullRequest, repo: Option<&str>, requested: &[String]) -> Result<Vec<String>> {
+    let author = github_user(github, config)?;
+    if !requested.is_empty() {
+        let available = match github.get_available_reviewers(repo, config.github.include_bots) {
+            Ok(available) => Some(available),
+            Err(err) => {
+                println!("{} Unable to list reviewers, requesting them unchecked: {}", "!".bright_yellow(), err);
+                None
+            }
+        };
+        let (reviewers, unknown) = requested_reviewers(requested, &config.github.default_reviewers, available.as_deref());
+        if !unknown.is_empty() {
+            println!("{} Skipping reviewers not assignable in the repository: {}", "!".bright_yellow(), unknown.join(", ").bright_cyan());
+        }
+        let reviewers = filter_reviewers(reviewers, &author, &config.github.exclude_reviewers);
+        ui::print_value("Reviewers", &reviewers.join(", "));
+        Ok(reviewers)
+    } else if let Some(auto) = &config.github.auto_reviewers {
+        let pool = filter_reviewers(auto.pool.clone(), &author, &config.github.exclude_reviewers);
+        let reviewers = pick_reviewers(pool, auto.count, &mut rand::thread_rng());
+        ui::print_value("Reviewers", &reviewers.join(", "));
+        Ok(reviewers)
+    } else {
+        let mut github_config = config.github.clone();
+        if !pr.reviewers.is_empty() {
+            github_config.default_reviewers = pr.reviewers.clone();
+        }
+        let mut available = match github.get_available_reviewers(repo, config.github.include_bots) {
+            Ok(available) => available,
+            Err(err) => {
+                println!("{} Unable to list reviewers: {}", "!".bright_yellow(), err);
+                let reviewers = prompter.reviewer_logins(&github_config)?;
+                return Ok(filter_reviewers(reviewers, &author, &config.github.exclude_reviewers));
+            }
+        };
+        // Teams and users suggested outside of the assignable users, eg. by CODEOWNERS.
+        for login in &pr.reviewers {
+            if !available.iter().any(|reviewer| &reviewer.login == login) {
+                available.push(Reviewer::new(login.clone()));
+            }
+        }
+        let reviewers = filter_reviewers(available, &author, &config.github.exclude_reviewers);
+        prompter.reviewers(&github_config, reviewers)
+    }
+}
+
+/// Union of `requested` and `defaults`, split into logins of `available` reviewers and unknown ones.
+/// `org/team` slugs aren't assignable users and are kept as is, every login is when `available` is unknown.
+fn requested_reviewers(requested: &[String], defaults: &[String], available: Option<&[Reviewer]>) -> (Vec<String>, Vec<String>) {
+    let mut reviewers: Vec<String> = Vec::new();
+    let mut unknown: Vec<String> = Vec::new();
+    for login in requested.iter().chain(defaults) {
+        if reviewers.contains(login) || unknown.contains(login) {
+            continue;
+        }
+        if login.contains('/') || available.is_none_or(|available| available.iter().any(|reviewer| &reviewer.login == login)) {
+            reviewers.push(login.clone());
+        } else {
+            unknown.push(login.clone());
+        }
+    }
+    (reviewers, unknown)
+}
+
+fn pick_reviewers<R: Rng>(mut pool: Vec<String>, count: usize, rng: &mut R) -> Vec<String> {
+    pool.sort();
+    pool.dedup();
+
+    pool.choose_multiple(rng, count).cloned().collect()
+}
+
+#[instrument(skip(github, config, prompter))]
+fn edit_pr(github: &dyn Forge, config: &Config, prompter: &dyn Prompter, repo: Option<&str>, number: Option<u32>) -> Result<()> {
+    let existing = match number {
+        Some(number) => github.get_pr_by_number(repo, number)?,
+        None => {
+            let branch = git::get_current_branch(&git::get_repository()?)?;
+            github.get_pr_by_branch(repo, &branch)?
+                .ok_or_else(|| Error::InvalidInput(format!("branch {} has no open PR, pass its number", branch)))?
+        }
+    };
+
+    rebuild_pr_body(github, config, prompter, &existing)
+}
+
+/// Creating a second PR for `branch` would be rejected by GitHub, offers to update `existing` instead.
+fn offer_existing_pr_update(github: &dyn Forge, config: &Config, prompter: &dyn Prompter, branch: &str, existing: &github::PullRequest) -> Result<()> {
+    let message = format!("PR #{} already exists for {}, update it?", existing.number, branch);
+    if !prompter.confirm(&message, true)? {
+        return Err(Error::InvalidInput(format!("branch {} already has PR #{}, edit it with `git-pr edit`", branch, existing.number)));
+    }
+
+    rebuild_pr_body(github, config, prompter, existing)
+}
+
+/// Re-runs the field prompts, pre-filled from the body of `existing`, and replaces its body.
+fn rebuild_pr_body(github: &dyn Forge, config: &Config, prompter: &dyn Prompter, existing: &github::PullRequest) -> Result<()> {
+    let tag = tags::extract_from_str(&existing.title).unwrap_or_default();
+    let mut pr = PullRequest {
+        title: existing.title.clone(),
+        branch: existing.head_ref_name.clone(),
+        base: existing.base_ref_name.clone(),
+        is_jira: config.is_tracked_jira_ticket(&tag),
+        tag,
+        ..Default::default()
+    };
+    ui::print_value("PR title", &pr.title);
+
+    let names: Vec<String> = config.template.fields.iter().map(|f| f.name.clone()).collect();
+    let current = template::extract_fields(&config.template.body, &existing.body, &names);
+
+    let placeholders = pr.placeholders();
+    for field in &config.template.fields {
+        let mut field = field.clone();
+        if let Some(value) = current.get(&field.name) {
+            field.default = Some(value.clone());
+        }
+        let value = prompter.field(&config.template, &field, &placeholders)?;
+        pr.fields.insert(field.name.clone(), value);
+    }
+
+    let body_opts = template::BodyOptions { tag: &pr.tag, is_jira: pr.is_jira, placeholders: Some(&placeholders), ..Default::default() };
+    let body = template::make_body(config, &pr.fields, &body_opts);
+    let body = template::carry_related_prs(config, &existing.body, &body);
+
+    let result = github.update_pr(&PrUpdate { number: existing.number, resource_path: &existing.resource_path, body: Some(&body), ..Default::default() })?;
+    println!("{} Updated #{}: {}", "+".bright_green(), existing.number, result);
+
+    Ok(())
+}
+
+/// Owners of the files changed since `base`, empty when the repository has no `CODEOWNERS`.
+fn codeowners_reviewers(config: &Config, base: &str) -> Result<Vec<String>> {
+    let repo = git::get_repository()?;
+    let Some(owners) = repo.workdir().map(CodeOwners::load).transpose()?.flatten() else {
+        return Ok(Vec::new());
+    };
+
+    let changed = git::changed_files(&repo, &config.github.remote, base)?;
+    let reviewers = owners.reviewers(changed.iter().map(String::as_str));
+    debug!(?reviewers, "codeowners of the changed files");
+
+    Ok(reviewers)
+}
+
+/// Runs `hooks.pre_create`, ahead of the prompts so a failing check wastes no answers.
+fn run_pre_create_hook(config: &Config, pr: &PullRequest) -> Result<()> {
+    let Some((program, args)) = config.hooks.pre_create_command() else {
+        return Ok(());
+    };
+    let placeholders = pr.placeholders();
+    let args: Vec<String> = args.iter().map(|arg| template::expand_placeholders(arg, &placeholders)).collect();
+    debug!(%program, ?args, "running pre_create hook");
+
+    let output = std::process::Command::new(&program)
+        .args(&args)
+        .stdin(std::process::Stdio::null())
+        .stdout(std::process::Stdio::inherit())
+        .output()
+        .map_err(|err| Error::InvalidInput(format!("unable to run the pre_create hook {}: {}", program, err)))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(Error::InvalidInput(format!("pre_create hook {} failed ({}): {}", program, output.status, stderr.trim())));
+    }
+
+    Ok(())
+}
+
+fn github_user(github: &dyn Forge, config: &Config) -> Result<String> {
+    match config.github_user() {
+        Some(user) => Ok(user),
+        None => github.get_authenticated_user(),
+    }
+}
+
+/// Drops the `author` and the `exclude`d logins from `reviewers`, logins or [`Reviewer`]s.
+fn filter_reviewers<T: AsRef<str>>(reviewers: Vec<T>, author: &str, exclude: &[String]) -> Vec<T> {
+    reviewers.into_iter()
+        .filter(|reviewer| !reviewer.as_ref().eq_ignore_ascii_case(author))
+        .filter(|reviewer| !exclude.iter().any(|login| login == reviewer.as_ref()))
+        .collect()
+}
+
+/// Per-run options of [`publish_pr`].
+#[derive(Debug, Default, Clone, Copy)]
+struct PublishOptions {
+    /// `--no-jira-link`
+    no_jira_link: bool,
+    /// `--web`, the compose page is opened instead of creating the PR.
+    web: bool,
+}
+
+fn publish_pr(github: &dyn Forge, config: &Config, repo: Option<&str>, pr: &PullRequest, opts: PublishOptions) -> Result<String> {
+    let placeholders = pr.placeholders();
+    let body_opts = template::BodyOptions {
+        tag: &pr.tag,
+        is_jira: pr.is_jira,
+        appendix: pr.appendix.as_deref(),
+        no_jira_link: opts.no_jira_link,
+        closes: &pr.closes,
+        placeholders: Some(&placeholders),
+    };
+    let body = template::make_body(config, &pr.fields, &body_opts);
+
+    github.publish_pr(config, &NewPr { repo, base: &pr.base, title: &pr.title, body: &body, reviewers: &pr.reviewers, web: opts.web })
+}
+
+/// Narrows the user's PRs considered related, besides sharing the tag.
+#[derive(Debug, Default)]
+struct RelatedScope<'a> {
+    /// `owner/name` related PRs belong to, any repository when missing.
+    repo: Option<&'a str>,
+    /// `YYYY-MM-DD` before which PRs last updated are left alone.
+    updated_since: Option<String>,
+}
+
+impl RelatedScope<'_> {
+    fn contains(&self, pr: &github::PullRequest) -> bool {
+        let in_repo = self.repo.is_none_or(|repo| github::repo_from_resource_path(&pr.resource_path).eq_ignore_ascii_case(repo));
+        let recent = self.updated_since.as_ref().is_none_or(|since| pr.updated_at.as_str() >= since.as_str());
+        in_repo && recent
+    }
+}
+
+/// `YYYY-MM-DD` of `days` before `now`, in UTC.
+fn days_ago(now: SystemTime, days: u64) -> String {
+    let elapsed = now.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs() / 86_400;
+    let (year, month, day) = civil_from_days(elapsed.saturating_sub(days) as i64);
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+/// Gregorian date of the day `days` after 1970-01-01, see http://howardhinnant.github.io/date_algorithms.html
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+fn filter_related_prs(prs: Vec<github::PullRequest>, tag: &str, scope: &RelatedScope) -> Vec<github::PullRequest> {
+    prs.into_iter()
+        .filter(|pr| tags::extract_from_str(pr.title.as_str()).as_deref() == Some(tag))
+        .filter(|pr| scope.contains(pr))
+        .collect()
+}
+
+#[instrument(skip(github, config))]
+fn update_related_prs(github: &dyn Forge, config: &Config, tag: &str, scope: &RelatedScope) -> Result<Vec<RelatedUpdate>> {
+    if config.tags.is_ignored(tag) {
+        debug!("tag is ignored, skipping related prs");
+        return Ok(Vec::new());
+    }
+    let related_prs = filter_related_prs(github.get_user_prs(&github_user(github, config)?)?, tag, scope);
+    debug!(count = related_prs.len(), "found related prs");
+
+    let mut updates = Vec::new();
+    for pr in &related_prs {
+        let updated_body = template::replace_related_prs(config, &pr.body, &pr.number, &related_prs);
+        let outcome = if updated_body == pr.body {
+            UpdateOutcome::Unchanged
+        } else {
+            match github.update_pr(&PrUpdate { number: pr.number, resource_path: &pr.resource_path, body: Some(&updated_body), ..Default::default() }) {
+                Ok(output) => UpdateOutcome::Updated(output),
+                Err(err) => UpdateOutcome::Failed(err.to_string()),
+            }
+        };
+        debug!(number = pr.number, ?outcome, "related pr processed");
+        updates.push(RelatedUpdate { number: pr.number, outcome });
+    }
+
+    Ok(updates)
+}
+
+/// Related PRs section rewrite of [`reconcile_related_prs`].
+#[derive(Debug)]
+struct Reconciled {
+    update: RelatedUpdate,
+    /// Lines of the body added and removed by the rewrite.
+    added: usize,
+    removed: usize,
+}
+
+/// Rewrites the related PRs section of every open PR tagged `tag` to the current set of related PRs,
+/// PRs missing the section get it appended.
+#[instrument(skip(github, config))]
+fn reconcile_related_prs(github: &dyn Forge, config: &Config, tag: &str, scope: &RelatedScope) -> Result<Vec<Reconciled>> {
+    if config.tags.is_ignored(tag) {
+        return Err(Error::InvalidInput(format!("tag {} is in tags.ignore, it has no related PRs", tag)));
+    }
+    let related_prs = filter_related_prs(github.get_user_prs(&github_user(github, config)?)?, tag, scope);
+    debug!(count = related_prs.len(), "found related prs");
+
+    let mut reconciled = Vec::new();
+    for pr in related_prs.iter().filter(|pr| pr.state == github::PullRequestState::Open) {
+        let body = template::with_related_prs_section(config, &pr.body);
+        let updated_body = template::replace_related_prs(config, &body, &pr.number, &related_prs);
+        let (added, removed) = changed_lines(&pr.body, &updated_body);
+        let outcome = if updated_body == pr.body {
+            UpdateOutcome::Unchanged
+        } else {
+            match github.update_pr(&PrUpdate { number: pr.number, resource_path: &pr.resource_path, body: Some(&updated_body), ..Default::default() }) {
+                Ok(output) => UpdateOutcome::Updated(output),
+                Err(err) => UpdateOutcome::Failed(err.to_string()),
+            }
+        };
+        debug!(number = pr.number, ?outcome, "pr reconciled");
+        reconciled.push(Reconciled { update: RelatedUpdate { number: pr.number, outcome }, added, removed });
+    }
+
+    Ok(reconciled)
+}
+
+/// Number of lines of `new` missing in `old` and of lines of `old` missing in `new`.
+fn changed_lines(old: &str, new: &str) -> (usize, usize) {
+    let missing = |from: &str, to: &str| {
+        let mut remaining: Vec<&str> = to.lines().collect();
+        from.lines()
+            .filter(|line| match remaining.iter().position(|l| l == line) {
+                Some(i) => {
+                    remaining.swap_remove(i);
+                    false
+                }
+                None => true,
+            })
+            .count()
+    };
+
+    (missing(new, old), missing(old, new))
+}
+
+fn print_reconciled_prs(tag: &str, reconciled: &[Reconciled]) {
+    if reconciled.is_empty() {
+        println!("{} No open prs tagged {} found", ">".bright_green(), tag.bright_cyan());
+        return;
+    }
+    println!("{} Reconciling {} open prs tagged {}", ">".bright_green(), reconciled.len(), tag.bright_cyan());
+
+    for pr in reconciled {
+        let number = pr.update.number;
+        match &pr.update.outcome {
+            UpdateOutcome::Unchanged => {
+                println!("{} Unchanged #{}", "=".bright_green(), number);
+            }
+            UpdateOutcome::Updated(e) => {
+                println!("{} Updated #{} (+{} -{} lines): {}", "+".bright_green(), number, pr.added, pr.removed, e);
+            }
+            UpdateOutcome::Failed(err) => {
+                println!("{} Updating #{} (+{} -{} lines) failed: {}", "x".red(), number, pr.added, pr.removed, err)
+            }
+        }
+    }
+}
+
+/// Rewrites `[from]` to `[to]` in titles of the user's PRs tagged `from`.
+#[instrument(skip(github, config))]
+fn retag_prs(github: &dyn Forge, config: &Config, retag: &Retag, scope: &RelatedScope) -> Result<Vec<RelatedUpdate>> {
+    let prs = filter_related_prs(github.get_user_prs(&github_user(github, config)?)?, &retag.from, scope);
+
+    let mut updates = Vec::new();
+    for pr in prs {
+        let title = pr.title.replacen(&format!("[{}]", retag.from), &format!("[{}]", retag.to), 1);
+        let outcome = match github.update_pr(&PrUpdate { number: pr.number, resource_path: &pr.resource_path, title: Some(&title), ..Default::default() }) {
+            Ok(output) => UpdateOutcome::Updated(output),
+            Err(err) => UpdateOutcome::Failed(err.to_string()),
+        };
+        debug!(number = pr.number, ?outcome, "pr retitled");
+        updates.push(RelatedUpdate { number: pr.number, outcome });
+    }
+
+    Ok(updates)
+}
+
+fn print_retitled_prs(retag: &Retag, updates: &[RelatedUpdate]) {
+    if updates.is_empty() {
+        println!("{} No prs tagged {} found", ">".bright_green(), retag.from.bright_cyan());
+        return;
+    }
+    println!("{} Retagging {} prs from {} to {}", ">".bright_green(), updates.len(), retag.from.bright_cyan(), retag.to.bright_cyan());
+
+    for update in updates {
+        match &update.outcome {
+            UpdateOutcome::Unchanged => {}
+            UpdateOutcome::Updated(e) => {
+                println!("{} Retitled #{}: {}", "+".bright_green(), update.number, e);
+            }
+            UpdateOutcome::Failed(err) => {
+                println!("{} Retitling #{} failed: {}", "x".red(), update.number, err)
+            }
+        }
+    }
+}
+
+fn print_related_updates(updates: &[RelatedUpdate]) {
+    if updates.is_empty() {
+        println!("{} No related prs found. Exiting...", ">".bright_green());
+        return;
+    }
+    println!("{} Found {} related prs. Updating... :)", ">".bright_green(), updates.len());
+
+    for update in updates {
+        match &update.outcome {
+            UpdateOutcome::Unchanged => {
+                println!("{} Unchanged #{}", "=".bright_green(), update.number);
+            }
+            UpdateOutcome::Updated(e) => {
+                println!("{} Updated #{}: {}", "+".bright_green(), update.number, e);
+            }
+            UpdateOutcome::Failed(err) => {
+                println!("{} Updated #{} failed: {}", "x".red(), update.number, err)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::{Cell, RefCell};
+    use std::time::Duration;
+
+    use crate::config::{AutoReviewers, FormField, GitHubConfig, TagsConfig, TemplateConfig};
+
+    use super::*;
+
+    struct ScriptedPrompter {
+        title: String,
+        tag: String,
+        base: String,
+        fields: HashMap<String, String>,
+        reviewers: Vec<String>,
+        confirm: bool,
+        /// `default_reviewers` the reviewers prompt was shown with.
+        default_reviewers: RefCell<Vec<String>>,
+    }
+
+    impl Prompter for ScriptedPrompter {
+        fn title(&self, _commits: &[String]) -> Result<String> {
+            Ok(self.title.clone())
+        }
+
+        fn tag(&self, _config: &TagsConfig, _history: &[String]) -> Result<String> {
+            Ok(self.tag.clone())
+        }
+
+        fn base(&self, _candidates: BaseCandidates) -> Result<String> {
+            Ok(self.base.clone())
+        }
+
+        /// Answers are checked like `prompt_field` does, an invalid one fails instead of re-prompting.
+        fn field(&self, _template: &TemplateConfig, field: &FormField, _placeholders: &HashMap<String, String>) -> Result<String> {
+            let value = self.fields.get(&field.name).cloned().or_else(|| field.default.clone()).ok_or(Error::Cancelled)?;
+            field.validate(&value).map_err(Error::InvalidInput)?;
+
+            Ok(value)
+        }
+
+        fn reviewers(&self, config: &GitHubConfig, _reviewers: Vec<Reviewer>) -> Result<Vec<String>> {
+            self.default_reviewers.replace(config.default_reviewers.clone());
+            Ok(self.reviewers.clone())
+        }
+
+        fn reviewer_logins(&self, _config: &GitHubConfig) -> Result<Vec<String>> {
+            Ok(self.reviewers.iter().map(|login| format!("typed-{}", login)).collect())
+        }
+
+        fn confirm(&self, _message: &str, _default: bool) -> Result<bool> {
+            Ok(self.confirm)
+        }
+    }
+
+    #[derive(Default)]
+    struct MockGitHub {
+        prs: Vec<github::PullRequest>,
+        reviewers: Vec<String>,
+        /// Updates of these PRs fail.
+        failing: Vec<u32>,
+        /// Titles and bodies of the published PRs.
+        published: RefCell<Vec<(String, String)>>,
+        updated: RefCell<Vec<(u32, String)>>,
+        retitled: RefCell<Vec<(u32, String)>>,
+        /// Open PR of the current branch.
+        existing: Option<github::PullRequest>,
+        /// Calls of `get_available_reviewers`.
+        reviewer_lookups: Cell<usize>,
+        /// `get_available_reviewers` fails.
+        reviewers_unavailable: bool,
+    }
+
+    impl Forge for MockGitHub {
+        fn publish_pr(&self, _config: &Config, pr: &NewPr) -> Result<String> {
+            self.published.borrow_mut().push((pr.title.to_string(), pr.body.to_string()));
+            Ok("https://github.com/owner/repo/pull/10".to_string())
+        }
+
+        fn update_pr(&self, update: &PrUpdate) -> Result<String> {
+            if self.failing.contains(&update.number) {
+                return Err(Error::GitHubCli("boom".to_string()));
+            }
+            if let Some(title) = update.title {
+                self.retitled.borrow_mut().push((update.number, title.to_string()));
+            }
+            if let Some(body) = update.body {
+                self.updated.borrow_mut().push((update.number, body.to_string()));
+            }
+            Ok(format!("https://github.com/owner/repo/pull/{}", update.number))
+        }
+
+        fn get_user_prs(&self, _login: &str) -> Result<Vec<github::PullRequest>> {
+            Ok(self.prs.clone())
+        }
+
+        fn get_available_reviewers(&self, _repo: Option<&str>, _include_bots: bool) -> Result<Vec<Reviewer>> {
+            self.reviewer_lookups.set(self.reviewer_lookups.get() + 1);
+            if self.reviewers_unavailable {
+                return Err(Error::GitHubCli("HTTP 401: Bad credentials".to_string()));
+            }
+            Ok(self.reviewers.iter().map(|login| Reviewer::new(login.clone())).collect())
+        }
+
+        fn get_authenticated_user(&self) -> Result<String> {
+            Ok("me".to_string())
+        }
+
+        fn get_pr_by_branch(&self, _repo: Option<&str>, _branch: &str) -> Result<Option<github::PullRequest>> {
+            Ok(self.existing.clone())
+        }
+
+        fn get_pr_by_number(&self, _repo: Option<&str>, number: u32) -> Result<github::PullRequest> {
+            self.prs.iter().find(|pr| pr.number == number).cloned().ok_or(Error::GitHubCli("not found".to_string()))
+        }
+
+        fn open_in_browser(&self, _url: &str) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    fn scripted() -> ScriptedPrompter {
+        ScriptedPrompter {
+            title: "Add things".to_string(),
+            tag: "TRACK-7".to_string(),
+            base: "develop".to_string(),
+            fields: HashMap::from([
+                ("description".to_string(), "Adds things".to_string()),
+                ("implementation".to_string(), "Carefully".to_string()),
+            ]),
+            reviewers: vec!["alice".to_string()],
+            confirm: true,
+            default_reviewers: RefCell::new(Vec::new()),
+        }
+    }
+
+    #[test]
+    fn test_run_config_command_without_repository() {
+        // Re-run in a child process outside of any repository, the cwd is shared by all tests.
+        if let Ok(file) = std::env::var("GIT_PR_TEST_CONFIG_FILE") {
+            for command in [ConfigCommand::Show, ConfigCommand::Validate] {
+                let args = Args {
+                    command: Some(Command::Config { command }),
+                    config_file: Some(PathBuf::from(&file)),
+                    ..Default::default()
+                };
+
+                run(&args, &scripted()).unwrap();
+            }
+            return;
+        }
+
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("config.yaml");
+        std::fs::write(&file, "github:\n  remote: upstream\n").unwrap();
+        assert!(git2::Repository::open(dir.path()).is_err());
+
+        // Path of this test relative to the crate, eg. `app::tests::test_run_config_command_without_repository`.
+        let name = format!("{}::test_run_config_command_without_repository", module_path!().split_once("::").unwrap().1);
+        let output = std::process::Command::new(std::env::current_exe().unwrap())
+            .args(["--exact", &name, "--nocapture"])
+            .env("GIT_PR_TEST_CONFIG_FILE", &file)
+            .current_dir(dir.path())
+            .output()
+            .unwrap();
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        assert!(output.status.success(), "{}", stdout);
+        // Also fails when the filter matches no test.
+        assert!(stdout.contains("remote: upstream"), "{}", stdout);
+    }
+
+    #[test]
+    fn test_build_pr_from_branch_prompts_without_tagged_commit() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut tags = Tags::from_file(dir.path().join("tags.txt")).unwrap();
+        let branch_info = BranchInfo {
+            branch: "feature".to_string(),
+            bases: vec!["main".to_string(), "develop".to_string()],
+            commits: vec!["Add things".to_string()],
+            ..Default::default()
+        };
+        let prompter = scripted();
+
+        let pr = build_pr_from_branch(&mut Config::default(), &prompter, &branch_info, &mut tags, None, false).unwrap();
+
+        assert_eq!(pr.title, "[TRACK-7]: Add things");
+        assert_eq!(pr.tag, "TRACK-7");
+        assert_eq!(pr.branch, "feature");
+        assert_eq!(select_base_branch(&prompter, &branch_info, Vec::new()).unwrap(), "develop");
+        assert_eq!(Tags::from_file(dir.path().join("tags.txt")).unwrap().iter(), vec!["TRACK-7".to_string()]);
+    }
+
+    #[test]
+    fn test_build_pr_from_branch_uses_tagged_commit() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut tags = Tags::from_file(dir.path().join("tags.txt")).unwrap();
+        let branch_info = BranchInfo {
+            branch: "feature".to_string(),
+            bases: vec!["main".to_string()],
+            commits: vec!["[TRACK-1]: Tagged".to_string()],
+            ..Default::default()
+        };
+
+        let pr = build_pr_from_branch(&mut Config::default(), &scripted(), &branch_info, &mut tags, None, false).unwrap();
+
+        assert_eq!(pr.title, "[TRACK-1]: Tagged");
+        assert_eq!(pr.tag, "TRACK-1");
+    }
+
+    #[test]
+    fn test_build_pr_from_branch_title_format() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut tags = Tags::from_file(dir.path().join("tags.txt")).unwrap();
+        let mut config = Config::default();
+        config.template.title_format = "{{title}} ([{{tag}}])".to_string();
+        let mut branch_info = BranchInfo {
+            branch: "feature".to_string(),
+            bases: vec!["main".to_string()],
+            commits: vec!["[TRACK-1] Tagged".to_string()],
+            ..Default::default()
+        };
+
+        let pr = build_pr_from_branch(&mut config, &scripted(), &branch_info, &mut tags, None, false).unwrap();
+        assert_eq!(pr.title, "Tagged ([TRACK-1])");
+
+        branch_info.commits = vec!["Untagged".to_string()];
+        let pr = build_pr_from_branch(&mut config, &scripted(), &branch_info, &mut tags, None, false).unwrap();
+        assert_eq!(pr.title, "Add things ([TRACK-7])");
+    }
+
+    #[test]
+    fn test_build_pr_from_branch_profile_title_format() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut tags = Tags::from_file(dir.path().join("tags.txt")).unwrap();
+        let yaml = r#"
+template:
+  profiles:
+    hotfix:
+      title_format: 'hotfix: {{title}} ({{tag}})'
+  profile_rules:
+    - prefix: BUG
+      profile: hotfix
+"#;
+        let mut config: Config = serde_yaml::from_str(yaml).unwrap();
+        let branch_info = BranchInfo {
+            branch: "feature".to_string(),
+            bases: vec!["main".to_string()],
+            commits: vec!["[BUG-1]: Fix it".to_string()],
+            ..Default::default()
+        };
+
+        let pr = build_pr_from_branch(&mut config, &scripted(), &branch_info, &mut tags, None, true).unwrap();
+        assert_eq!(pr.title, "hotfix: Fix it (BUG-1)");
+        assert!(config.template.profiles.is_empty());
+    }
+
+    #[test]
+    fn test_build_pr_from_branch_already_tagged() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut tags = Tags::from_file(dir.path().join("tags.txt")).unwrap();
+        let branch_info = BranchInfo {
+            branch: "feature".to_string(),
+            bases: vec!["main".to_string()],
+            commits: vec!["[TRACK-1]: [TRACK-1]: Tagged twice".to_string()],
+            ..Default::default()
+        };
+
+        let pr = build_pr_from_branch(&mut Config::default(), &scripted(), &branch_info, &mut tags, None, false).unwrap();
+        assert_eq!(pr.title, "[TRACK-1]: Tagged twice");
+
+        let prompter = ScriptedPrompter { title: "[TRACK-7]: Add things".to_string(), ..scripted() };
+        let pr = build_pr_from_branch(&mut Config::default(), &prompter, &branch_info, &mut tags, Some("TRACK-7"), false).unwrap();
+        assert_eq!(pr.title, "[TRACK-7]: Add things");
+    }
+
+    #[test]
+    fn test_build_pr_from_branch_keeps_brackets() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut tags = Tags::from_file(dir.path().join("tags.txt")).unwrap();
+        let branch_info = BranchInfo {
+            branch: "feature".to_string(),
+            bases: vec!["main".to_string()],
+            commits: vec!["Untagged".to_string()],
+            ..Default::default()
+        };
+
+        let prompter = ScriptedPrompter { title: "Use array[0] indexing".to_string(), ..scripted() };
+        let pr = build_pr_from_branch(&mut Config::default(), &prompter, &branch_info, &mut tags, Some("TRACK-7"), false).unwrap();
+        assert_eq!(pr.title, "[TRACK-7]: Use array[0] indexing");
+    }
+
+    #[test]
+    fn test_build_pr_from_branch_tag_override() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut tags = Tags::from_file(dir.path().join("tags.txt")).unwrap();
+        let branch_info = BranchInfo {
+            branch: "feature".to_string(),
+            bases: vec!["main".to_string()],
+            commits: vec!["[TRACK-1]: Tagged".to_string()],
+            ..Default::default()
+        };
+
+        let pr = build_pr_from_branch(&mut Config::default(), &scripted(), &branch_info, &mut tags, Some("TRACK-123"), false).unwrap();
+
+        assert_eq!(pr.title, "[TRACK-123]: Add things");
+        assert_eq!(pr.tag, "TRACK-123");
+        assert_eq!(Tags::from_file(dir.path().join("tags.txt")).unwrap().iter(), vec!["TRACK-123".to_string()]);
+
+        let invalid = build_pr_from_branch(&mut Config::default(), &scripted(), &branch_info, &mut tags, Some("[TRACK-123]"), false);
+        assert!(matches!(invalid, Err(Error::InvalidInput(_))));
+    }
+
+    #[test]
+    fn test_gather_pr_details() {
+        let mut config = Config::default();
+        config.github.user = Some("me".to_string());
+        config.github.auto_reviewers = Some(AutoReviewers {
+            pool: vec!["me".to_string(), "bob".to_string()],
+            count: 1,
+        });
+        let mut pr = PullRequest::default();
+
+        gather_pr_details(&MockGitHub::default(), &config, &scripted(), &mut pr, None, &Answers::default(), None).unwrap();
+
+        assert_eq!(pr.fields.get("description").unwrap(), "Adds things");
+        assert_eq!(pr.fields.get("implementation").unwrap(), "Carefully");
+        assert_eq!(pr.reviewers, vec!["bob".to_string()]);
+    }
+
+    #[test]
+    fn test_gather_pr_details_default_fields() {
+        let mut config = Config::default();
+        config.github.user = Some("me".to_string());
+        let mut prompter = scripted();
+        prompter.fields = HashMap::from([
+            ("description".to_string(), String::new()),
+            ("implementation".to_string(), " ".to_string()),
+        ]);
+        let mut pr = PullRequest::default();
+
+        gather_pr_details(&MockGitHub::default(), &config, &prompter, &mut pr, None, &Answers::default(), None).unwrap();
+        assert_eq!(pr.fields.get("description").unwrap(), "");
+        assert_eq!(pr.fields.get("implementation").unwrap(), " ");
+
+        config.template.fields[0].required = true;
+        let mut pr = PullRequest::default();
+        let missing = gather_pr_details(&MockGitHub::default(), &config, &prompter, &mut pr, None, &Answers::default(), None);
+        assert!(matches!(missing, Err(Error::InvalidInput(msg)) if msg == "description is required"));
+
+        prompter.fields.insert("description".to_string(), "Adds things".to_string());
+        gather_pr_details(&MockGitHub::default(), &config, &prompter, &mut pr, None, &Answers::default(), None).unwrap();
+        assert_eq!(pr.fields.get("description").unwrap(), "Adds things");
+        assert_eq!(pr.fields.get("implementation").unwrap(), " ");
+    }
+
+    #[test]
+    fn test_gather_pr_details_custom_fields() {
+        let mut config = Config::default();
+        config.github.user = Some("me".to_string());
+        config.template.body = "## Risk\n\n{{risk}}\n\n## Rollback\n\n{{rollback}}\n".to_string();
+        config.template.fields = vec![
+            FormField { name: "risk".to_string(), ..Default::default() },
+            FormField { name: "rollback".to_string(), default: Some("Revert".to_string()), ..Default::default() },
+        ];
+        let mut prompter = scripted();
+        prompter.fields = HashMap::from([("risk".to_string(), "Low".to_string())]);
+        let mut pr = PullRequest::default();
+
+        gather_pr_details(&MockGitHub::default(), &config, &prompter, &mut pr, None, &Answers::default(), None).unwrap();
+
+        assert_eq!(pr.fields, HashMap::from([
+            ("risk".to_string(), "Low".to_string()),
+            ("rollback".to_string(), "Revert".to_string()),
+        ]));
+    }
+
+    #[test]
+    fn test_build_gather_publish() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut tags = Tags::from_file(dir.path().join("tags.txt")).unwrap();
+        let mut config = Config::default();
+        config.github.user = Some("me".to_string());
+        config.jira.url = Some("https://example.atlassian.net/browse/".to_string());
+        let branch_info = BranchInfo {
+            branch: "feature".to_string(),
+            bases: vec!["develop".to_string()],
+            commits: vec!["Add things".to_string()],
+            ..Default::default()
+        };
+        let github = MockGitHub { reviewers: vec!["alice".to_string()], ..Default::default() };
+        let prompter = scripted();
+
+        let mut pr = build_pr_from_branch(&mut config, &prompter, &branch_info, &mut tags, None, false).unwrap();
+        gather_pr_details(&github, &config, &prompter, &mut pr, None, &Answers::default(), None).unwrap();
+        publish_pr(&github, &config, None, &pr, PublishOptions::default()).unwrap();
+
+        let (title, body) = github.published.borrow()[0].clone();
+        assert_eq!(title, "[TRACK-7]: Add things");
+        assert!(body.starts_with("Tracked by [TRACK-7](https://example.atlassian.net/browse/TRACK-7)\n"));
+        assert!(body.contains("## This PR...\n\nAdds things\n"));
+        assert!(body.contains("## Considerations and implementation\n\nCarefully"));
+    }
+
+    #[test]
+    fn test_gather_pr_details_requested_reviewers() {
+        let mut config = Config::default();
+        config.github.user = Some("me".to_string());
+        config.github.default_reviewers = vec!["carol".to_string(), "bob".to_string()];
+        let github = MockGitHub {
+            reviewers: vec!["alice".to_string(), "bob".to_string(), "carol".to_string()],
+            ..Default::default()
+        };
+        let prompter = scripted();
+        let mut pr = PullRequest::default();
+
+        let requested = vec!["bob".to_string(), "ghost".to_string(), "org/team".to_string()];
+        let answers = Answers { reviewers: &requested, ..Default::default() };
+        gather_pr_details(&github, &config, &prompter, &mut pr, None, &answers, None).unwrap();
+
+        assert_eq!(pr.reviewers, vec!["bob", "org/team", "carol"]);
+        assert!(prompter.default_reviewers.borrow().is_empty());
+    }
+
+    #[test]
+    fn test_gather_pr_details_reviewers_unavailable() {
+        let mut config = Config::default();
+        config.github.user = Some("me".to_string());
+        let github = MockGitHub { reviewers_unavailable: true, ..Default::default() };
+        let mut pr = PullRequest::default();
+
+        gather_pr_details(&github, &config, &scripted(), &mut pr, None, &Answers::default(), None).unwrap();
+        assert_eq!(pr.reviewers, vec!["typed-alice".to_string()]);
+
+        let requested = vec!["bob".to_string()];
+        let answers = Answers { reviewers: &requested, ..Default::default() };
+        gather_pr_details(&github, &config, &scripted(), &mut pr, None, &answers, None).unwrap();
+        assert_eq!(pr.reviewers, requested);
+    }
+
+    #[test]
+    fn test_gather_pr_details_no_reviewers() {
+        let github = MockGitHub { reviewers: vec!["alice".to_string()], ..Default::default() };
+        let mut pr = PullRequest { reviewers: vec!["bob".to_string()], ..Default::default() };
+        let answers = Answers { no_reviewers: true, ..Default::default() };
+
+        gather_pr_details(&github, &Config::default(), &scripted(), &mut pr, None, &answers, None).unwrap();
+
+        assert!(pr.reviewers.is_empty());
+        assert_eq!(github.reviewer_lookups.get(), 0);
+
+        // The flag wins over configured reviewers.
+        let mut config = Config::default();
+        config.github.default_reviewers = vec!["alice".to_string()];
+        config.github.auto_reviewers = Some(AutoReviewers { pool: vec!["alice".to_string()], count: 1 });
+        gather_pr_details(&github, &config, &scripted(), &mut pr, None, &answers, None).unwrap();
+
+        assert!(pr.reviewers.is_empty());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_run_pre_create_hook() {
+        let mut config = Config::default();
+        let pr = PullRequest { tag: "TRACK-7".to_string(), branch: "feature".to_string(), ..Default::default() };
+        run_pre_create_hook(&config, &pr).unwrap();
+
+        config.hooks.pre_create = Some("test {{tag}} = TRACK-7".to_string());
+        run_pre_create_hook(&config, &pr).unwrap();
+
+        config.hooks.pre_create = Some("ls /nonexistent/{{branch}}".to_string());
+        let Err(Error::InvalidInput(message)) = run_pre_create_hook(&config, &pr) else {
+            panic!("expected the hook to fail");
+        };
+        assert!(message.starts_with("pre_create hook ls failed"));
+        assert!(message.contains("/nonexistent/feature"));
+
+        config.hooks.pre_create = Some("/nonexistent/hook".to_string());
+        assert!(matches!(run_pre_create_hook(&config, &pr), Err(Error::InvalidInput(_))));
+    }
+
+    #[test]
+    fn test_read_field_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("description.md");
+        std::fs::write(&path, "Generated notes\n\n").unwrap();
+        let mut config = Config::default();
+
+        let files = vec![FieldFile { name: "description".to_string(), path: path.clone() }];
+        let fields = read_field_files(&config, &files).unwrap();
+        assert_eq!(fields.get("description").unwrap(), "Generated notes");
+
+        let unknown = vec![FieldFile { name: "summary".to_string(), path: path.clone() }];
+        let Err(Error::InvalidInput(message)) = read_field_files(&config, &unknown) else {
+            panic!("expected an unknown field");
+        };
+        assert_eq!(message, "unknown field summary in --field-file, template fields: description, implementation");
+
+        config.template.fields[0].min_length = Some(100);
+        assert!(matches!(read_field_files(&config, &files), Err(Error::InvalidInput(_))));
+
+        let missing = vec![FieldFile { name: "description".to_string(), path: dir.path().join("missing.md") }];
+        assert!(matches!(read_field_files(&Config::default(), &missing), Err(Error::Io(_))));
+    }
+
+    #[test]
+    fn test_gather_pr_details_commit_body_default() {
+        let mut prompter = scripted();
+        prompter.fields.remove("description");
+        let mut pr = PullRequest {
+            commits: vec!["[AB-1]: Add things\n\nAdds the things\nwe need.".to_string()],
+            ..Default::default()
+        };
+
+        gather_pr_details(&MockGitHub::default(), &Config::default(), &prompter, &mut pr, None, &Answers::default(), None).unwrap();
+        assert_eq!(pr.fields.get("description").unwrap(), "Adds the things\nwe need.");
+
+        let mut config = Config::default();
+        config.template.fields[0].default = Some("Configured".to_string());
+        let mut configured = PullRequest { fields: HashMap::new(), ..pr };
+        gather_pr_details(&MockGitHub::default(), &config, &prompter, &mut configured, None, &Answers::default(), None).unwrap();
+        assert_eq!(configured.fields.get("description").unwrap(), "Configured");
+    }
+
+    #[test]
+    fn test_gather_pr_details_given_fields() {
+        let mut prompter = scripted();
+        prompter.fields.remove("description");
+        let mut pr = PullRequest::default();
+
+        let answers = Answers { fields: HashMap::from([("description".to_string(), "From a file".to_string())]), ..Default::default() };
+        gather_pr_details(&MockGitHub::default(), &Config::default(), &prompter, &mut pr, None, &answers, None).unwrap();
+
+        assert_eq!(pr.fields.get("description").unwrap(), "From a file");
+        assert_eq!(pr.fields.get("implementation").unwrap(), "Carefully");
+    }
+
+    #[test]
+    fn test_requested_reviewers() {
+        let available = vec![Reviewer::new("alice"), Reviewer::new("bob")];
+        let requested = vec!["bob".to_string(), "ghost".to_string(), "bob".to_string()];
+        let defaults = vec!["alice".to_string(), "ghost".to_string()];
+
+        let (reviewers, unknown) = requested_reviewers(&requested, &defaults, Some(&available));
+        assert_eq!(reviewers, vec!["bob", "alice"]);
+        assert_eq!(unknown, vec!["ghost"]);
+
+        let (reviewers, unknown) = requested_reviewers(&requested, &defaults, None);
+        assert_eq!(reviewers, vec!["bob", "ghost", "alice"]);
+        assert!(unknown.is_empty());
+    }
+
+    #[test]
+    fn test_resume_draft() {
+        let dir = tempfile::tempdir().unwrap();
+        let draft = PullRequest {
+            branch: "feature".to_string(),
+            fields: HashMap::from([("description".to_string(), "Drafted".to_string())]),
+            reviewers: vec!["bob".to_string()],
+            ..Default::default()
+        };
+        draft.save_draft(dir.path()).unwrap();
+
+        let mut prompter = scripted();
+        prompter.fields.remove("description");
+        let mut pr = PullRequest { branch: "feature".to_string(), ..Default::default() };
+
+        resume_draft(&prompter, dir.path(), &mut pr).unwrap();
+        gather_pr_details(&MockGitHub::default(), &Config::default(), &prompter, &mut pr, None, &Answers::default(), Some(dir.path())).unwrap();
+
+        assert_eq!(pr.fields.get("description").unwrap(), "Drafted");
+        assert_eq!(pr.fields.get("implementation").unwrap(), "Carefully");
+        assert_eq!(*prompter.default_reviewers.borrow(), vec!["bob".to_string()]);
+
+        let saved = PullRequest::load_draft(dir.path(), "feature").unwrap().unwrap();
+        assert_eq!(saved.fields, pr.fields);
+        assert_eq!(saved.reviewers, vec!["alice".to_string()]);
+
+        let mut pr = PullRequest { branch: "feature".to_string(), ..Default::default() };
+        resume_draft(&ScriptedPrompter { confirm: false, ..scripted() }, dir.path(), &mut pr).unwrap();
+        assert!(pr.fields.is_empty());
+    }
+
+    #[test]
+    fn test_ensure_base_differs() {
+        assert!(ensure_base_differs("main", "feature").is_ok());
+        assert!(matches!(ensure_base_differs("feature", "feature"), Err(Error::InvalidInput(_))));
+    }
+
+    #[test]
+    fn test_filter_reviewers_excludes_author() {
+        let reviewers = vec!["me".to_string(), "alice".to_string(), "ci-bot".to_string(), "bob".to_string()];
+
+        let filtered = filter_reviewers(reviewers, "Me", &["ci-bot".to_string()]);
+
+        assert_eq!(filtered, vec!["alice".to_string(), "bob".to_string()]);
+    }
+
+    #[test]
+    fn test_pick_reviewers() {
+        use rand::SeedableRng;
+
+        let pool = vec!["alice".to_string(), "bob".to_string(), "alice".to_string(), "carol".to_string()];
+        let pool = filter_reviewers(pool, "carol", &[]);
+
+        for seed in 0..20 {
+            let mut rng = rand::rngs::StdRng::seed_from_u64(seed);
+            let mut picked = pick_reviewers(pool.clone(), 2, &mut rng);
+            picked.sort();
+
+            assert_eq!(picked, vec!["alice".to_string(), "bob".to_string()]);
+        }
+
+        let mut rng = rand::rngs::StdRng::seed_from_u64(0);
+        assert_eq!(pick_reviewers(pool, 5, &mut rng).len(), 2);
+    }
+
+    fn pull_request(number: u32, title: &str) -> github::PullRequest {
+        github::PullRequest {
+            id: number.to_string(),
+            title: title.to_string(),
+            resource_path: format!("/owner/repo/pull/{}", number),
+            number,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_filter_related_prs_exact_tag() {
+        let prs = vec![
+            pull_request(1, "[AB-1]: first"),
+            pull_request(2, "[AB-12]: mentions AB-1 in title"),
+            pull_request(3, "Untagged AB-1"),
+            pull_request(4, "[AB-1]: second"),
+        ];
+
+        let related: Vec<u32> = filter_related_prs(prs, "AB-1", &RelatedScope::default()).iter().map(|pr| pr.number).collect();
+
+        assert_eq!(related, vec![1, 4]);
+    }
+
+    #[test]
+    fn test_filter_related_prs_scope() {
+        let prs = vec![
+            github::PullRequest { updated_at: "2024-03-01T10:00:00Z".to_string(), ..pull_request(1, "[AB-1]: first") },
+            github::PullRequest { resource_path: "/other/repo/pull/2".to_string(), updated_at: "2024-03-01T10:00:00Z".to_string(), ..pull_request(2, "[AB-1]: elsewhere") },
+            github::PullRequest { updated_at: "2024-01-01T10:00:00Z".to_string(), ..pull_request(3, "[AB-1]: old") },
+        ];
+        let scope = RelatedScope { repo: Some("Owner/Repo"), updated_since: Some("2024-02-01".to_string()) };
+
+        let related: Vec<u32> = filter_related_prs(prs.clone(), "AB-1", &scope).iter().map(|pr| pr.number).collect();
+        assert_eq!(related, vec![1]);
+
+        let related: Vec<u32> = filter_related_prs(prs, "AB-1", &RelatedScope { repo: Some("owner/repo"), ..Default::default() }).iter().map(|pr| pr.number).collect();
+        assert_eq!(related, vec![1, 3]);
+    }
+
+    #[test]
+    fn test_days_ago() {
+        let now = UNIX_EPOCH + Duration::from_secs(1_709_290_800); // 2024-03-01T11:00:00Z
+        assert_eq!(days_ago(now, 0), "2024-03-01");
+        assert_eq!(days_ago(now, 1), "2024-02-29");
+        assert_eq!(days_ago(now, 366), "2023-03-01");
+        assert_eq!(days_ago(UNIX_EPOCH, 5), "1970-01-01");
+    }
+
+    #[test]
+    fn test_update_related_prs() {
+        let config = Config::default();
+        let related = [pull_request(1, "[AB-1]: first"), pull_request(3, "[AB-1]: second"), pull_request(4, "[AB-1]: third")];
+        let stale = template::replace_related_prs(&config, template::TEMPLATE, &1, &related[..1]);
+        let github = MockGitHub {
+            prs: vec![
+                github::PullRequest { body: stale.clone(), ..related[0].clone() },
+                pull_request(2, "[AB-12]: other"),
+                github::PullRequest { body: template::replace_related_prs(&config, template::TEMPLATE, &3, &related), ..related[1].clone() },
+                github::PullRequest { body: stale, ..related[2].clone() },
+            ],
+            failing: vec![4],
+            ..Default::default()
+        };
+
+        let updates = update_related_prs(&github, &config, "AB-1", &RelatedScope::default()).unwrap();
+
+        let numbers: Vec<u32> = updates.iter().map(|u| u.number).collect();
+        assert_eq!(numbers, vec![1, 3, 4]);
+        assert!(matches!(updates[0].outcome, UpdateOutcome::Updated(_)));
+        assert!(matches!(updates[1].outcome, UpdateOutcome::Unchanged));
+        assert!(matches!(updates[2].outcome, UpdateOutcome::Failed(_)));
+
+        let updated = github.updated.borrow();
+        assert_eq!(updated.len(), 1);
+        assert_eq!(updated[0].0, 1);
+        assert!(updated[0].1.contains("- owner/repo/pull/1 - (this pr)\n- owner/repo/pull/3\n- owner/repo/pull/4\n"));
+    }
+
+    #[test]
+    fn test_reconcile_related_prs() {
+        let config = Config::default();
+        let merged = github::PullRequest { state: github::PullRequestState::Merged, ..pull_request(3, "[AB-1]: merged") };
+        let related = [pull_request(1, "[AB-1]: first"), pull_request(2, "[AB-1]: second"), merged];
+        let current = template::replace_related_prs(&config, template::TEMPLATE, &2, &related);
+        let github = MockGitHub {
+            prs: vec![
+                github::PullRequest { body: "Adds things.".to_string(), ..related[0].clone() },
+                github::PullRequest { body: current, ..related[1].clone() },
+                related[2].clone(),
+                pull_request(4, "[AB-12]: other"),
+            ],
+            ..Default::default()
+        };
+
+        let reconciled = reconcile_related_prs(&github, &config, "AB-1", &RelatedScope::default()).unwrap();
+
+        let numbers: Vec<u32> = reconciled.iter().map(|r| r.update.number).collect();
+        assert_eq!(numbers, vec![1, 2]);
+        assert!(matches!(reconciled[0].update.outcome, UpdateOutcome::Updated(_)));
+        assert_eq!((reconciled[0].added, reconciled[0].removed), (7, 0));
+        assert!(matches!(reconciled[1].update.outcome, UpdateOutcome::Unchanged));
+        assert_eq!((reconciled[1].added, reconciled[1].removed), (0, 0));
+
+        let updated = github.updated.borrow();
+        assert_eq!(updated.len(), 1);
+        assert_eq!(updated[0].1, "Adds things.\n\nRelated PRs:\n<!-- RELATED_PR -->\n- owner/repo/pull/1 - (this pr)\n- owner/repo/pull/2\n- owner/repo/pull/3 (merged)\n<!-- /RELATED_PR -->\n");
+
+        let mut ignoring = Config::default();
+        ignoring.tags.ignore = vec!["AB-1".to_string()];
+        assert!(matches!(reconcile_related_prs(&github, &ignoring, "AB-1", &RelatedScope::default()), Err(Error::InvalidInput(_))));
+    }
+
+    #[test]
+    fn test_changed_lines() {
+        assert_eq!(changed_lines("a\nb\nc", "a\nb\nc"), (0, 0));
+        assert_eq!(changed_lines("a\n- x\nc", "a\n- y\n- z\nc"), (2, 1));
+        assert_eq!(changed_lines("a\na", "a"), (0, 1));
+    }
+
+    #[test]
+    fn test_offer_existing_pr_update() {
+        let config = Config::default();
+        let existing = pull_request(5, "[AB-1]: existing");
+        let github = MockGitHub::default();
+
+        offer_existing_pr_update(&github, &config, &scripted(), "feature", &existing).unwrap();
+
+        let updated = github.updated.borrow();
+        assert_eq!(updated.len(), 1);
+        assert_eq!(updated[0].0, 5);
+        assert!(updated[0].1.contains("Adds things"));
+        drop(updated);
+
+        let declined = ScriptedPrompter { confirm: false, ..scripted() };
+        let result = offer_existing_pr_update(&github, &config, &declined, "feature", &existing);
+        assert!(matches!(result, Err(Error::InvalidInput(_))));
+        assert!(github.published.borrow().is_empty());
+    }
+
+    #[test]
+    fn test_rebuild_pr_body_placeholders() {
+        let mut config = Config::default();
+        config.template.body = "{{description}}\n\nFrom {{branch}} into {{base}}\n".to_string();
+        let existing = github::PullRequest {
+            head_ref_name: "feature".to_string(),
+            base_ref_name: "main".to_string(),
+            ..pull_request(5, "[AB-1]: existing")
+        };
+        let github = MockGitHub::default();
+
+        rebuild_pr_body(&github, &config, &scripted(), &existing).unwrap();
+        assert!(github.updated.borrow()[0].1.starts_with("Adds things\n\nFrom feature into main\n"));
+    }
+
+    #[test]
+    fn test_retag_prs() {
+        let config = Config::default();
+        let github = MockGitHub {
+            prs: vec![
+                pull_request(1, "[OLD-1]: first"),
+                pull_request(2, "[OLD-12]: other"),
+                pull_request(3, "[OLD-1]: second, see [OLD-1]"),
+                pull_request(4, "[OLD-1]: third"),
+            ],
+            failing: vec![4],
+            ..Default::default()
+        };
+        let retag = Retag { from: "OLD-1".to_string(), to: "NEW-1".to_string() };
+
+        let updates = retag_prs(&github, &config, &retag, &RelatedScope::default()).unwrap();
+
+        let numbers: Vec<u32> = updates.iter().map(|u| u.number).collect();
+        assert_eq!(numbers, vec![1, 3, 4]);
+        assert!(matches!(updates[2].outcome, UpdateOutcome::Failed(_)));
+        assert_eq!(*github.retitled.borrow(), vec![
+            (1, "[NEW-1]: first".to_string()),
+            (3, "[NEW-1]: second, see [OLD-1]".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn test_update_related_prs_scoped_to_repo() {
+        let config = Config::default();
+        let github = MockGitHub {
+            prs: vec![
+                pull_request(1, "[INFRA-1]: here"),
+                github::PullRequest { resource_path: "/owner/other/pull/2".to_string(), ..pull_request(2, "[INFRA-1]: elsewhere") },
+                pull_request(3, "[INFRA-1]: here too"),
+            ],
+            ..Default::default()
+        };
+
+        let updates = update_related_prs(&github, &config, "INFRA-1", &RelatedScope { repo: Some("owner/repo"), ..Default::default() }).unwrap();
+
+        let numbers: Vec<u32> = updates.iter().map(|u| u.number).collect();
+        assert_eq!(numbers, vec![1, 3]);
+        for (_, body) in github.updated.borrow().iter() {
+            assert!(!body.contains("owner/other"));
+        }
+    }
+
+    #[test]
+    fn test_update_related_prs_ignored_tag() {
+        let mut config = Config::default();
+        config.tags.ignore = vec!["NOJIRA".to_string()];
+        let github = MockGitHub {
+            prs: vec![pull_request(1, "[NOJIRA]: first"), pull_request(2, "[NOJIRA]: second")],
+            ..Default::default()
+        };
+
+        let updates = update_related_prs(&github, &config, "NOJIRA", &RelatedScope::default()).unwrap();
+
+        assert!(updates.is_empty());
+        assert!(github.updated.borrow().is_empty());
+    }
+
+    #[test]
+    fn test_update_related_prs_skips_unchanged() {
+        let config = Config::default();
+        let related = [pull_request(1, "[AB-1]: first"), pull_request(2, "[AB-1]: second")];
+        let prs: Vec<github::PullRequest> = related.iter().map(|pr| github::PullRequest {
+            body: template::replace_related_prs(&config, template::TEMPLATE, &pr.number, &related),
+            ..pr.clone()
+        }).collect();
+        let github = MockGitHub { prs, ..Default::default() };
+
+        let updates = update_related_prs(&github, &config, "AB-1", &RelatedScope::default()).unwrap();
+
+        assert_eq!(updates.len(), 2);
+        assert!(updates.iter().all(|u| matches!(u.outcome, UpdateOutcome::Unchanged)));
+        assert!(github.updated.borrow().is_empty());
+    }
+
+    #[test]
+    fn test_create_publishes_and_updates_related() {
+        let config = Config::default();
+        let github = MockGitHub {
+            prs: vec![pull_request(1, "[TRACK-7]: earlier")],
+            ..Default::default()
+        };
+        let pr = PullRequest {
+            title: "[TRACK-7]: Add things".to_string(),
+            tag: "TRACK-7".to_string(),
+            ..Default::default()
+        };
+
+        let created = create(&github, &config, &pr, &CreateOptions { update_related: true, ..Default::default() }).unwrap();
+
+        assert_eq!(created.url, "https://github.com/owner/repo/pull/10");
+        assert_eq!(github.published.borrow()[0].0, "[TRACK-7]: Add things");
+        assert_eq!(created.related.len(), 1);
+
+        let created = create(&github, &config, &pr, &CreateOptions::default()).unwrap();
+        assert!(created.related.is_empty());
+    }
+}
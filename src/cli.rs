@@ -1,10 +1,16 @@
-use clap::Parser;
+use std::path::PathBuf;
+
+use clap::{Parser, Subcommand, ValueEnum};
 use serde::{Deserialize, Serialize};
 
 #[derive(Parser, Debug, Default, Serialize, Deserialize)]
 #[serde(default, deny_unknown_fields)]
 #[clap(author, version, about, long_about = None)]
 pub struct Args {
+    #[clap(subcommand)]
+    #[serde(skip_serializing, skip_deserializing)]
+    pub command: Option<Command>,
+
     #[clap(short, long, value_parser, default_value_t = false)]
     #[serde(skip_serializing, skip_deserializing)]
     pub update_only: bool,
@@ -12,4 +18,234 @@ pub struct Args {
     #[clap(short, long, value_parser, default_value_t = false)]
     #[serde(skip_serializing, skip_deserializing)]
     pub dry_run: bool,
+
+    /// With --dry-run, write the PR body to this file and bodies of related PRs next to it
+    #[clap(long, value_parser, requires = "dry_run")]
+    #[serde(skip_serializing, skip_deserializing)]
+    pub out: Option<PathBuf>,
+
+    /// Open the created PR in the browser
+    #[clap(short, long, value_parser, default_value_t = false)]
+    #[serde(skip_serializing, skip_deserializing)]
+    pub open: bool,
+
+    /// Copy the created PR url to the clipboard
+    #[clap(long, value_parser, default_value_t = false)]
+    #[serde(skip_serializing, skip_deserializing)]
+    pub copy: bool,
+
+    /// Skip updating related PRs sharing the same tag
+    #[clap(long, value_parser, default_value_t = false, conflicts_with = "update_only")]
+    #[serde(skip_serializing, skip_deserializing)]
+    pub no_related: bool,
+
+    /// Number of reviewers picked at random from `github.auto_reviewers.pool`
+    #[clap(long, value_parser)]
+    #[serde(skip_serializing, skip_deserializing)]
+    pub reviewer_team_size: Option<usize>,
+
+    /// Scan branches even when `git.cache` is enabled
+    #[clap(long, value_parser, default_value_t = false)]
+    #[serde(skip_serializing, skip_deserializing)]
+    pub no_cache: bool,
+
+    /// Tag of the PR, skips tag detection
+    #[clap(long, value_parser)]
+    #[serde(skip_serializing, skip_deserializing)]
+    pub tag: Option<String>,
+
+    /// Value of a template field read from a file instead of prompting, eg. `description=notes.md`,
+    /// `-` reads stdin, repeatable
+    #[clap(long = "field-file", value_parser = parse_field_file, conflicts_with = "update_only")]
+    #[serde(skip_serializing, skip_deserializing)]
+    pub field_files: Vec<FieldFile>,
+
+    /// Append the content of this file to the PR body, `-` reads stdin, see `template.append_position`
+    #[clap(long, value_parser, conflicts_with = "update_only")]
+    #[serde(skip_serializing, skip_deserializing)]
+    pub append_body: Option<PathBuf>,
+
+    /// Issue closed by the PR once merged, rendered by `{{closes}}` as `Closes #N`, repeatable
+    #[clap(long, value_parser = parse_issue, conflicts_with = "update_only")]
+    #[serde(skip_serializing, skip_deserializing)]
+    pub closes: Vec<u32>,
+
+    /// Leave the Jira tracking line out of the PR body
+    #[clap(long, value_parser, default_value_t = false, conflicts_with = "update_only")]
+    #[serde(skip_serializing, skip_deserializing)]
+    pub no_jira_link: bool,
+
+    /// Open the GitHub compose page pre-filled with the title and body instead of creating the PR,
+    /// reviewers and draft are set on the page
+    #[clap(long, value_parser, default_value_t = false, conflicts_with_all = ["update_only", "reviewers", "draft", "open", "copy"])]
+    #[serde(skip_serializing, skip_deserializing)]
+    pub web: bool,
+
+    /// Open the PR as a draft
+    #[clap(long, value_parser, default_value_t = false)]
+    #[serde(skip_serializing, skip_deserializing)]
+    pub draft: bool,
+
+    /// Milestone of the PR, by title, overrides `github.default_milestone`
+    #[clap(long, value_parser, conflicts_with = "update_only")]
+    #[serde(skip_serializing, skip_deserializing)]
+    pub milestone: Option<String>,
+
+    /// Only update related PRs updated in the last <SINCE> days
+    #[clap(long, value_parser)]
+    #[serde(skip_serializing, skip_deserializing)]
+    pub since: Option<u64>,
+
+    /// Pre-select owners of the changed files from CODEOWNERS as reviewers
+    #[clap(long, value_parser, default_value_t = false)]
+    #[serde(skip_serializing, skip_deserializing)]
+    pub reviewers_from_codeowners: bool,
+
+    /// Reviewer to request, repeatable, skips the reviewers prompt
+    #[clap(short, long = "reviewer", value_parser)]
+    #[serde(skip_serializing, skip_deserializing)]
+    pub reviewers: Vec<String>,
+
+    /// Open the PR without reviewers, skips looking them up and the reviewers prompt,
+    /// `github.default_reviewers`, `github.auto_reviewers` and CODEOWNERS included
+    #[clap(long, value_parser, default_value_t = false, conflicts_with_all = ["reviewers", "reviewer_team_size", "reviewers_from_codeowners"])]
+    #[serde(skip_serializing, skip_deserializing)]
+    pub no_reviewers: bool,
+
+    /// Profile of `template.profiles` used for the PR body and fields,
+    /// picked by `template.profile_rules` or `default` when missing
+    #[clap(long, value_parser)]
+    #[serde(skip_serializing, skip_deserializing)]
+    pub template: Option<String>,
+
+    /// Base branch of the PR, skips base autodetection
+    #[clap(long, value_parser)]
+    #[serde(skip_serializing, skip_deserializing)]
+    pub base: Option<String>,
+
+    /// Repository the `gh` commands target, instead of the one of `github.remote`
+    #[clap(long, value_parser = parse_repo)]
+    #[serde(skip_serializing, skip_deserializing)]
+    pub repo: Option<String>,
+
+    /// Config file to load instead of `~/.config/git-pr/config.yaml`, read as YAML whatever its name
+    #[clap(long, value_parser)]
+    #[serde(skip_serializing, skip_deserializing)]
+    pub config_file: Option<PathBuf>,
+
+    /// Print the config directory, config file and tags file in use, then exit
+    #[clap(long, value_parser, default_value_t = false)]
+    #[serde(skip_serializing, skip_deserializing)]
+    pub print_config_path: bool,
+
+    /// Rewrites the `[OLD]` tag in titles of your PRs to `[NEW]`, eg. `--retag OLD-1=NEW-1`
+    #[clap(long, value_parser = parse_retag, requires = "update_only")]
+    #[serde(skip_serializing, skip_deserializing)]
+    pub retag: Option<Retag>,
+
+    /// When to color the output, `auto` colors a terminal unless `NO_COLOR` is set
+    #[clap(long, value_enum, default_value_t = ColorChoice::Auto)]
+    #[serde(skip_serializing, skip_deserializing)]
+    pub color: ColorChoice,
+
+    /// Same as `--color never`
+    #[clap(long, value_parser, default_value_t = false, conflicts_with = "color")]
+    #[serde(skip_serializing, skip_deserializing)]
+    pub no_color: bool,
+
+    /// Log filter written to stderr, eg. `debug` or `git_pr=trace`, overrides `RUST_LOG`
+    #[clap(long, value_parser)]
+    #[serde(skip_serializing, skip_deserializing)]
+    pub log_level: Option<String>,
+}
+
+#[derive(ValueEnum, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ColorChoice {
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+impl Args {
+    /// Whether to color the output, `no_color_env` is a non-empty `NO_COLOR`.
+    pub fn use_color(&self, no_color_env: bool, terminal: bool) -> bool {
+        match self.color {
+            _ if self.no_color => false,
+            ColorChoice::Auto => terminal && !no_color_env,
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+        }
+    }
+}
+
+/// Tag rename of `--retag`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Retag {
+    pub from: String,
+    pub to: String,
+}
+
+/// Field value file of `--field-file`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldFile {
+    pub name: String,
+    pub path: PathBuf,
+}
+
+fn parse_field_file(value: &str) -> Result<FieldFile, String> {
+    match value.split_once('=') {
+        Some((name, path)) if !name.is_empty() && !path.is_empty() => Ok(FieldFile { name: name.to_string(), path: PathBuf::from(path) }),
+        _ => Err(format!("expected NAME=PATH, got {}", value)),
+    }
+}
+
+fn parse_retag(value: &str) -> Result<Retag, String> {
+    match value.split_once('=') {
+        Some((from, to)) if !from.is_empty() && !to.is_empty() => Ok(Retag { from: from.to_string(), to: to.to_string() }),
+        _ => Err(format!("expected OLD=NEW, got {}", value)),
+    }
+}
+
+fn parse_issue(value: &str) -> Result<u32, String> {
+    value.strip_prefix('#').unwrap_or(value)
+        .parse()
+        .map_err(|_| format!("expected an issue number, eg. 123 or #123, got {}", value))
+}
+
+fn parse_repo(value: &str) -> Result<String, String> {
+    match value.split_once('/') {
+        Some((owner, name)) if !owner.is_empty() && !name.is_empty() && !name.contains('/') => Ok(value.to_string()),
+        _ => Err(format!("expected OWNER/NAME, got {}", value)),
+    }
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Re-run the field prompts and rebuild the body of an existing PR
+    Edit {
+        /// Number of the PR, defaults to the open PR of the current branch
+        number: Option<u32>,
+    },
+    /// Rewrite the related PRs section of every open PR tagged TAG, adding it where it's missing
+    Reconcile {
+        /// Tag of the PRs, eg. TRACK-123
+        #[clap(long, value_parser)]
+        tag: String,
+    },
+    /// Inspect the config file
+    Config {
+        #[clap(subcommand)]
+        command: ConfigCommand,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ConfigCommand {
+    /// Check the config for mistakes
+    Validate,
+    /// Print the config with environment overrides applied
+    Show,
+    /// Print the JSON schema of the config file
+    Schema,
 }
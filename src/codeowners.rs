@@ -0,0 +1,156 @@
+use std::path::Path;
+
+use regex::Regex;
+
+use crate::error::Result;
+
+/// Locations GitHub reads the `CODEOWNERS` file from, first found wins.
+const LOCATIONS: &[&str] = &[".github/CODEOWNERS", "CODEOWNERS", "docs/CODEOWNERS"];
+
+/// Rules of a `CODEOWNERS` file.
+#[derive(Debug, Default)]
+pub(crate) struct CodeOwners {
+    rules: Vec<Rule>,
+}
+
+#[derive(Debug)]
+struct Rule {
+    pattern: Regex,
+    owners: Vec<String>,
+}
+
+impl CodeOwners {
+    /// Loads the `CODEOWNERS` file of the repository checked out at `root`, `None` when it has none.
+    pub(crate) fn load(root: &Path) -> Result<Option<Self>> {
+        for location in LOCATIONS {
+            let path = root.join(location);
+            if path.is_file() {
+                return Ok(Some(Self::parse(&std::fs::read_to_string(path)?)));
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Lines with a pattern that doesn't translate to a regex are skipped, like GitHub does.
+    pub(crate) fn parse(content: &str) -> Self {
+        let rules = content.lines()
+            .map(|line| line.split_once(" #").map_or(line, |(rule, _)| rule).trim())
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .filter_map(|line| {
+                let mut parts = line.split_whitespace();
+                let pattern = pattern_regex(parts.next()?)?;
+                Some(Rule { pattern, owners: parts.map(String::from).collect() })
+            })
+            .collect();
+
+        Self { rules }
+    }
+
+    /// Owners of `path`, relative to the repository root. The last matching rule wins,
+    /// a rule without owners leaves the path unowned.
+    pub(crate) fn owners(&self, path: &str) -> &[String] {
+        self.rules.iter()
+            .rev()
+            .find(|rule| rule.pattern.is_match(path))
+            .map(|rule| rule.owners.as_slice())
+            .unwrap_or_default()
+    }
+
+    /// Reviewer logins and `org/team` slugs owning any of `paths`, in order of appearance.
+    /// Owners given by email can't be requested for review and are skipped.
+    pub(crate) fn reviewers<'a>(&self, paths: impl IntoIterator<Item = &'a str>) -> Vec<String> {
+        let mut reviewers: Vec<String> = Vec::new();
+        for path in paths {
+            for owner in self.owners(path) {
+                let Some(login) = owner.strip_prefix('@') else {
+                    continue;
+                };
+                if !reviewers.iter().any(|r| r == login) {
+                    reviewers.push(login.to_string());
+                }
+            }
+        }
+        reviewers
+    }
+}
+
+/// Translates a gitignore style pattern: patterns containing a `/` other than a trailing one
+/// are relative to the root, others match at any depth. Matching a directory matches everything in it.
+fn pattern_regex(pattern: &str) -> Option<Regex> {
+    let anchored = pattern.trim_end_matches('/').contains('/');
+    let pattern = pattern.trim_start_matches('/');
+
+    let mut regex = String::from(if anchored { "^" } else { "^(?:.*/)?" });
+    let mut chars = pattern.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '*' if chars.peek() == Some(&'*') => {
+                chars.next();
+                if chars.peek() == Some(&'/') {
+                    chars.next();
+                    regex.push_str("(?:.*/)?");
+                } else {
+                    regex.push_str(".*");
+                }
+            }
+            '*' => regex.push_str("[^/]*"),
+            '?' => regex.push_str("[^/]"),
+            c => regex.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    if !regex.ends_with('/') {
+        regex.push_str("(?:/|$)");
+    }
+
+    Regex::new(&regex).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CODEOWNERS: &str = "\
+# Default owners
+*               @org/core
+*.rs            @alice   # rust
+/docs/          @bob
+src/ui/**/*.rs  @carol @org/ui
+vendor/
+config.yaml     dave@example.com
+";
+
+    #[test]
+    fn test_owners_last_match_wins() {
+        let owners = CodeOwners::parse(CODEOWNERS);
+
+        assert_eq!(owners.owners("README.md"), ["@org/core"]);
+        assert_eq!(owners.owners("src/main.rs"), ["@alice"]);
+        assert_eq!(owners.owners("docs/guide/intro.md"), ["@bob"]);
+        assert_eq!(owners.owners("src/docs/intro.md"), ["@org/core"]);
+        assert_eq!(owners.owners("src/ui/prompt.rs"), ["@carol", "@org/ui"]);
+        assert_eq!(owners.owners("src/ui/widgets/list.rs"), ["@carol", "@org/ui"]);
+        assert!(owners.owners("vendor/lib/a.c").is_empty());
+    }
+
+    #[test]
+    fn test_reviewers() {
+        let owners = CodeOwners::parse(CODEOWNERS);
+
+        let reviewers = owners.reviewers(["src/ui/prompt.rs", "src/main.rs", "src/ui/list.rs", "config.yaml"]);
+        assert_eq!(reviewers, vec!["carol", "org/ui", "alice"]);
+    }
+
+    #[test]
+    fn test_load() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(CodeOwners::load(dir.path()).unwrap().is_none());
+
+        std::fs::write(dir.path().join("CODEOWNERS"), "* @root").unwrap();
+        std::fs::create_dir(dir.path().join(".github")).unwrap();
+        std::fs::write(dir.path().join(".github/CODEOWNERS"), "* @github").unwrap();
+
+        let owners = CodeOwners::load(dir.path()).unwrap().unwrap();
+        assert_eq!(owners.owners("a.txt"), ["@github"]);
+    }
+}
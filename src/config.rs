@@ -1,15 +1,834 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::path::{Path, PathBuf};
 
+use lazy_static::lazy_static;
+use regex::{Captures, Regex};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::error::Error;
+use crate::template;
+
 const PKG_NAME: &str = "git-pr";
+const CONFIG_FILE: &str = "config.yaml";
+/// Template profile used without `--template`.
+pub const DEFAULT_TEMPLATE: &str = "default";
+
+/// Environment variable overriding a config key, flags take precedence over it.
+struct EnvOverride {
+    var: &'static str,
+    key: &'static str,
+    apply: fn(&mut Config, String),
+}
+
+const ENV_OVERRIDES: &[EnvOverride] = &[
+    EnvOverride { var: "JIRA_URL", key: "jira.url", apply: |config, value| config.jira.url = Some(value) },
+    EnvOverride { var: "GITHUB_USER", key: "github.user", apply: |config, value| config.github.user = Some(value) },
+    EnvOverride { var: "GIT_PR_DEFAULT_BASE", key: "github.default_base", apply: |config, value| config.github.default_base = Some(value) },
+    EnvOverride { var: "GIT_PR_DEFAULT_REVIEWERS", key: "github.default_reviewers", apply: |config, value| config.github.default_reviewers = split_list(&value) },
+    EnvOverride { var: "GIT_PR_DRAFT", key: "github.draft", apply: |config, value| config.github.draft = parse_bool(&value) },
+    EnvOverride { var: "GIT_PR_TEMPLATE", key: "template.body_file", apply: |config, value| config.template.body_file = Some(value) },
+];
+
+lazy_static! {
+    /// `${VAR}` or `${VAR:-default}` in config values.
+    static ref ENV_REFERENCE: Regex = Regex::new(r"\$\{([A-Za-z_][A-Za-z0-9_]*)(?::-([^}]*))?\}").unwrap();
+}
+
+/// Values of keys containing one of these are never printed.
+const SECRET_KEYS: &[&str] = &["token", "password", "secret"];
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Where the repositories are hosted, the `github` section applies to GitLab merge requests too.
+    pub forge: ForgeKind,
+    pub git: GitConfig,
+    pub github: GitHubConfig,
+    pub jira: JiraConfig,
+    pub markers: MarkersConfig,
+    pub tags: TagsConfig,
+    pub template: TemplateConfig,
+    pub hooks: HooksConfig,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ForgeKind {
+    /// GitHub, through `gh`.
+    #[default]
+    GitHub,
+    /// GitLab, through `glab`.
+    GitLab,
+}
+
+#[derive(Debug, Clone, Hash, Serialize, Deserialize, JsonSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct GitConfig {
+    /// Reuse the branch scan while HEAD and the branches don't move, see `--no-cache`.
+    pub cache: bool,
+    /// Number of commits of every branch searched for the PR base, unlimited when missing.
+    /// Speeds up repositories with many branches, a base forked deeper than that is not detected.
+    pub base_scan_limit: Option<usize>,
+    /// Leave merge commits out of the commits offered as PR title.
+    pub skip_merge_commits: bool,
+}
+
+impl Default for GitConfig {
+    fn default() -> Self {
+        Self {
+            cache: false,
+            base_scan_limit: None,
+            skip_merge_commits: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct GitHubConfig {
+    /// Login of the PR author, resolved with `gh api user` when missing.
+    pub user: Option<String>,
+    /// Assign the PR to the authenticated user (`-a @me`).
+    /// Disable it when running under a machine account.
+    pub self_assign: bool,
+    /// Additional logins assigned to every created PR.
+    pub assignees: Vec<String>,
+    /// Remote the PR is opened against, owner/repo is derived from its url.
+    /// Set it to eg. `upstream` in fork based workflows.
+    pub remote: String,
+    /// Require at least one reviewer to be selected.
+    pub require_reviewer: bool,
+    /// Reviewers pre-selected in the reviewers prompt.
+    pub default_reviewers: Vec<String>,
+    /// Logins never offered as reviewers.
+    pub exclude_reviewers: Vec<String>,
+    /// Offer bot accounts (`dependabot[bot]`, ...) as reviewers, they can't review so they're hidden by default.
+    pub include_bots: bool,
+    /// Picks reviewers at random instead of prompting.
+    pub auto_reviewers: Option<AutoReviewers>,
+    /// Reviewers shown at once in the reviewers prompt.
+    pub reviewer_page_size: usize,
+    /// Pre-selects owners of the changed files from `CODEOWNERS` in the reviewers prompt,
+    /// same as `--reviewers-from-codeowners`.
+    pub reviewers_from_codeowners: bool,
+    /// Open the created PR in the browser, same as `--open`.
+    pub open_after_create: bool,
+    /// Copy the created PR url to the clipboard, same as `--copy`.
+    pub copy_url: bool,
+    /// Base of the PR instead of autodetecting it, `--base` takes precedence.
+    pub default_base: Option<String>,
+    /// Open PRs as drafts, same as `--draft`.
+    pub draft: bool,
+    /// Title of the milestone of created PRs, `--milestone` takes precedence.
+    pub default_milestone: Option<String>,
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct AutoReviewers {
+    /// Logins reviewers are picked from.
+    pub pool: Vec<String>,
+    /// Number of reviewers to pick.
+    pub count: usize,
+}
+
+impl Default for GitHubConfig {
+    fn default() -> Self {
+        Self {
+            user: None,
+            self_assign: true,
+            assignees: Vec::new(),
+            remote: "origin".to_string(),
+            require_reviewer: true,
+            default_reviewers: Vec::new(),
+            exclude_reviewers: Vec::new(),
+            include_bots: false,
+            auto_reviewers: None,
+            reviewer_page_size: 10,
+            reviewers_from_codeowners: false,
+            open_after_create: false,
+            copy_url: false,
+            default_base: None,
+            draft: false,
+            default_milestone: None,
+        }
+    }
+}
+
+impl GitHubConfig {
+    pub fn assignees(&self) -> Vec<String> {
+        let mut assignees = Vec::new();
+        if self.self_assign {
+            assignees.push("@me".to_string());
+        }
+        assignees.extend(self.assignees.iter().cloned());
+        assignees
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct JiraConfig {
+    /// Base URL the ticket key is appended to, eg. `https://example.atlassian.net/browse/`.
+    pub url: Option<String>,
+    /// Tags matching this pattern are treated as Jira tickets.
+    pub key_pattern: String,
+    /// Link to the ticket, `{{url}}` (without trailing slashes) and `{{tag}}` are expanded,
+    /// eg. `{{url}}/browse/{{tag}}`. The tag is appended to `url` when missing.
+    pub link_template: Option<String>,
+    /// Line linking the Jira ticket, `{{tag}}`, `{{url}}` and `{{link}}` are expanded. Empty disables it.
+    pub tracking_line_template: String,
+    /// Where the tracking line is put in the body.
+    pub tracking_line_position: TrackingLinePosition,
+}
+
+impl Default for JiraConfig {
+    fn default() -> Self {
+        Self {
+            url: None,
+            key_pattern: r"^[A-Z]+-\d+$".to_string(),
+            link_template: None,
+            tracking_line_template: "Tracked by [{{tag}}]({{link}})".to_string(),
+            tracking_line_position: TrackingLinePosition::default(),
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum TrackingLinePosition {
+    /// Replaces the line of the template body holding `<!-- ISSUE_URL -->`, prepends without it.
+    #[default]
+    Marker,
+    /// First line of the body.
+    Prepend,
+    /// Last line of the body.
+    Append,
+}
+
+impl JiraConfig {
+    /// Url of the `tag` ticket.
+    pub fn link(&self, tag: &str) -> String {
+        let url = self.url.as_deref().unwrap_or_default().trim_end_matches('/');
+        match &self.link_template {
+            Some(link_template) => {
+                let values = HashMap::from([
+                    ("url".to_string(), url.to_string()),
+                    ("tag".to_string(), tag.to_string()),
+                ]);
+                template::expand_placeholders(link_template, &values)
+            }
+            None => format!("{}/{}", url, tag),
+        }
+    }
+
+    pub fn is_jira_ticket(&self, tag: &str) -> bool {
+        if self.url.as_deref().unwrap_or_default().is_empty() {
+            return false;
+        }
+
+        match Regex::new(&self.key_pattern) {
+            Ok(re) => re.is_match(tag),
+            Err(_) => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct MarkersConfig {
+    /// Marker opening the related PRs section.
+    pub start: String,
+    /// Marker closing the related PRs section.
+    pub end: String,
+    /// Wrap the related PRs section in a `<details>` block.
+    pub collapsible: bool,
+    /// Summary of the `<details>` block, `{count}` is replaced with the number of related PRs.
+    pub summary_format: String,
+    /// Line of the related PRs section while there are none, empty leaves the section empty.
+    pub empty_placeholder: String,
+}
+
+impl Default for MarkersConfig {
+    fn default() -> Self {
+        Self {
+            start: "<!-- RELATED_PR -->".to_string(),
+            end: "<!-- /RELATED_PR -->".to_string(),
+            collapsible: false,
+            summary_format: "Related PRs ({count})".to_string(),
+            empty_placeholder: "No related PRs yet".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum TagRanking {
+    /// Most recently used first.
+    Recency,
+    /// Most used first.
+    Frequency,
+    /// Most used first, with uses losing half of their weight every two weeks.
+    #[default]
+    Hybrid,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum TagsScope {
+    /// `tags.txt` of the config directory, shared by every repository.
+    #[default]
+    Global,
+    /// `git-pr-tags.txt` in the `.git` directory of the repository.
+    Repo,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct TagsConfig {
+    /// Tags given with `--tag` have to match this pattern.
+    pub pattern: String,
+    /// Order of the tag suggestions.
+    pub ranking: TagRanking,
+    /// Where the tag history is kept.
+    pub scope: TagsScope,
+    /// Tags, eg. `NOJIRA` or `WIP`, never getting related PRs updates or the Jira tracking line.
+    /// Compared case-insensitively.
+    pub ignore: Vec<String>,
+}
+
+impl Default for TagsConfig {
+    fn default() -> Self {
+        Self {
+            pattern: r"^\w+(-\w+)*$".to_string(),
+            ranking: TagRanking::default(),
+            scope: TagsScope::default(),
+            ignore: Vec::new(),
+        }
+    }
+}
+
+impl TagsConfig {
+    pub fn validate(&self, tag: &str) -> Result<(), String> {
+        let re = Regex::new(&self.pattern).map_err(|err| format!("invalid tags.pattern: {}", err))?;
+        if !re.is_match(tag) {
+            return Err(format!("tag {} does not match {}", tag, self.pattern));
+        }
+
+        Ok(())
+    }
+
+    pub fn is_ignored(&self, tag: &str) -> bool {
+        self.ignore.iter().any(|ignored| ignored.eq_ignore_ascii_case(tag))
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct TemplateConfig {
+    /// PR body, `{{name}}` placeholders are replaced with values of the matching fields.
+    pub body: String,
+    /// Fields prompted for when creating a PR, in order.
+    pub fields: Vec<FormField>,
+    /// How multi-line fields are entered.
+    pub editor_mode: EditorMode,
+    /// What to do when the editor of `external` mode fails to start, eg. on a headless machine.
+    pub editor_fallback: EditorFallback,
+    /// Editor used in `external` mode, falls back to `$VISUAL` and then `$EDITOR`.
+    /// It's a program path followed by whitespace separated arguments, eg. `code --wait`.
+    /// Shell quoting is not supported.
+    pub editor_command: Option<String>,
+    /// File the body is read from, replaces `body`.
+    pub body_file: Option<String>,
+    /// Line of every PR in the related PRs list, `{{number}}`, `{{title}}`, `{{path}}` (`owner/repo/pull/1`),
+    /// `{{status}}` (` (merged)`, ` (closed)` or ` (draft)`, empty for open PRs)
+    /// and `{{this_pr}}` (` - (this pr)` for the PR being updated) are expanded.
+    pub related_pr_item: String,
+    /// Named templates selected with `--template`, keys missing in a profile take their defaults,
+    /// not the values of this template. This template is the `default` profile unless one is listed.
+    pub profiles: BTreeMap<String, TemplateConfig>,
+    /// Profiles picked by the tag of the PR when `--template` is not given, first match wins.
+    pub profile_rules: Vec<ProfileRule>,
+    /// Where the content of `--append-body` goes.
+    pub append_position: AppendPosition,
+    /// PR title, `{{tag}}` and `{{title}}` (the commit or typed title without its tag) are expanded.
+    /// Related PRs are found by the `[TAG]` of their titles, formats without it leave them out.
+    pub title_format: String,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum AppendPosition {
+    /// End of the body.
+    #[default]
+    End,
+    /// Right before the related PRs section, at the end without it.
+    BeforeRelated,
+    /// Right after the related PRs section, at the end without it.
+    AfterRelated,
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct ProfileRule {
+    /// Project key of the tag, the part before the first `-`, matched case-insensitively, eg. `BUG`.
+    pub prefix: String,
+    /// Name of the profile in `template.profiles`.
+    pub profile: String,
+}
+
+impl ProfileRule {
+    pub fn matches(&self, tag: &str) -> bool {
+        tag.split('-').next().is_some_and(|key| key.eq_ignore_ascii_case(&self.prefix))
+    }
+}
+
+impl Default for TemplateConfig {
+    fn default() -> Self {
+        Self {
+            body: template::TEMPLATE.to_string(),
+            fields: vec![
+                FormField {
+                    name: "description".to_string(),
+                    message: "What is this PR doing: ".to_string(),
+                    field_type: FieldType::Editor,
+                    ..Default::default()
+                },
+                FormField {
+                    name: "implementation".to_string(),
+                    message: "Considerations and implementation: ".to_string(),
+                    field_type: FieldType::Editor,
+                    ..Default::default()
+                },
+            ],
+            editor_mode: EditorMode::default(),
+            editor_fallback: EditorFallback::default(),
+            editor_command: None,
+            body_file: None,
+            related_pr_item: "- {{path}}{{status}}{{this_pr}}".to_string(),
+            profiles: BTreeMap::new(),
+            profile_rules: Vec::new(),
+            append_position: AppendPosition::default(),
+            title_format: "[{{tag}}]: {{title}}".to_string(),
+        }
+    }
+}
+
+impl TemplateConfig {
+    /// Placeholders of `body` matching neither a field nor a reserved placeholder.
+    pub fn unknown_placeholders(&self) -> Vec<String> {
+        let mut unknown: Vec<String> = Vec::new();
+        for name in template::placeholder_names(&self.body) {
+            let known = self.fields.iter().any(|field| field.name == name)
+                || template::RESERVED_PLACEHOLDERS.contains(&name.as_str());
+            if !known && !unknown.contains(&name) {
+                unknown.push(name);
+            }
+        }
+        unknown
+    }
+
+    /// Names accepted by `--template`.
+    pub fn profile_names(&self) -> Vec<String> {
+        let mut names = vec![DEFAULT_TEMPLATE.to_string()];
+        names.extend(self.profiles.keys().filter(|name| *name != DEFAULT_TEMPLATE).cloned());
+        names
+    }
+
+    /// Profile of the first rule of `profile_rules` matching `tag`.
+    pub fn profile_for_tag(&self, tag: &str) -> Option<&str> {
+        self.profile_rules.iter()
+            .find(|rule| rule.matches(tag))
+            .map(|rule| rule.profile.as_str())
+    }
+
+    fn load_body_file(&mut self, key: &str) -> Result<(), ::config::ConfigError> {
+        if let Some(path) = &self.body_file {
+            self.body = std::fs::read_to_string(path)
+                .map_err(|err| ::config::ConfigError::Message(format!("unable to read {}.body_file {}: {}", key, path, err)))?;
+        }
+        for (name, profile) in self.profiles.iter_mut() {
+            profile.load_body_file(&format!("{}.profiles.{}", key, name))?;
+        }
+
+        Ok(())
+    }
+
+    /// Resolved editor program and its arguments.
+    pub fn editor_command(&self) -> Option<(String, Vec<String>)> {
+        let command = self.editor_command.clone()
+            .or_else(|| std::env::var("VISUAL").ok())
+            .or_else(|| std::env::var("EDITOR").ok())?;
+
+        split_command(&command)
+    }
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct HooksConfig {
+    /// Command run before creating a PR, a non-zero exit aborts the creation. It's a program followed by
+    /// whitespace separated arguments, eg. `./scripts/check-branch {{branch}}`, run without a shell.
+    /// `{{tag}}`, `{{branch}}`, `{{base}}`, `{{commits}}` and `{{closes}}` of the arguments are expanded.
+    pub pre_create: Option<String>,
+}
+
+impl HooksConfig {
+    /// Program and arguments of `pre_create`.
+    pub fn pre_create_command(&self) -> Option<(String, Vec<String>)> {
+        split_command(self.pre_create.as_deref()?)
+    }
+}
+
+fn split_command(command: &str) -> Option<(String, Vec<String>)> {
+    let mut parts = command.split_whitespace().map(String::from);
+    let program = parts.next()?;
+
+    Some((program, parts.collect()))
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum EditorMode {
+    /// Opens `$EDITOR`.
+    #[default]
+    External,
+    /// Line by line prompt in the terminal, finished with an empty line.
+    Inline,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum EditorFallback {
+    /// Line by line prompt in the terminal, as in `inline` mode.
+    #[default]
+    Inline,
+    /// Default of the field, the error is kept when it has none or it's invalid.
+    Default,
+    /// Fails with the error of the editor.
+    Error,
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct FormField {
+    /// Name of the `{{name}}` placeholder in the template body.
+    pub name: String,
+    /// Prompt shown to the user.
+    pub message: String,
+    #[serde(rename = "type")]
+    pub field_type: FieldType,
+    pub required: bool,
+    pub default: Option<String>,
+    pub min_length: Option<usize>,
+    pub max_length: Option<usize>,
+    /// Regex the value has to match.
+    pub pattern: Option<String>,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum FieldType {
+    /// Single line prompt.
+    #[default]
+    Text,
+    /// Multi-line prompt, see `template.editor_mode`.
+    Editor,
+}
+
+impl FormField {
+    pub fn validate(&self, value: &str) -> Result<(), String> {
+        let length = value.trim().chars().count();
+        if length == 0 {
+            if self.required {
+                return Err(format!("{} is required", self.name));
+            }
+            return Ok(());
+        }
+
+        if let Some(min) = self.min_length {
+            if length < min {
+                return Err(format!("{} must be at least {} characters long", self.name, min));
+            }
+        }
+        if let Some(max) = self.max_length {
+            if length > max {
+                return Err(format!("{} must be at most {} characters long", self.name, max));
+            }
+        }
+        if let Some(pattern) = &self.pattern {
+            let re = Regex::new(pattern).map_err(|err| format!("invalid pattern for {}: {}", self.name, err))?;
+            if !re.is_match(value) {
+                return Err(format!("{} must match {}", self.name, pattern));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Config {
+    /// Loads `file` when given, it's read as YAML whatever its extension and must exist.
+    /// Otherwise the optional `config.yaml` of the config directory is loaded.
+    pub fn load(file: Option<&Path>) -> Result<Self, ::config::ConfigError> {
+        let source = match file {
+            Some(file) => ::config::File::new(&file.to_string_lossy(), ::config::FileFormat::Yaml).required(true),
+            None => ::config::File::from(PathBuf::from(get_config_path())).required(false),
+        };
+
+        let mut config: Self = ::config::Config::builder()
+            .add_source(source)
+            .build()?
+            .try_deserialize()?;
+
+        config.interpolate_env(&|name| std::env::var(name).ok())?;
+        config.apply_env_overrides(&|name| std::env::var(name).ok());
+        config.load_body_file()?;
+
+        Ok(config)
+    }
+
+    /// Checks the config for mistakes which would produce broken PRs.
+    pub fn validate(&self) -> crate::error::Result<()> {
+        let mut problems = Vec::new();
+
+        let body = &self.template.body;
+        for (marker, configured) in [("<!-- RELATED_PR -->", &self.markers.start), ("<!-- /RELATED_PR -->", &self.markers.end)] {
+            if !body.contains(marker) && !body.contains(configured.as_str()) {
+                problems.push(format!("template.body is missing the {} marker", marker));
+            }
+        }
+
+        let mut names = HashSet::new();
+        for field in &self.template.fields {
+            if !names.insert(field.name.as_str()) {
+                problems.push(format!("template.fields contains {} more than once", field.name));
+            }
+            let placeholder = format!("{{{{{}}}}}", field.name);
+            if !body.contains(&placeholder) {
+                problems.push(format!("field {} is not used, add {} to template.body", field.name, placeholder));
+            }
+            if let Some(Err(err)) = field.pattern.as_deref().map(Regex::new) {
+                problems.push(format!("pattern of field {} does not compile: {}", field.name, err));
+            }
+        }
+
+        let unknown = self.template.unknown_placeholders();
+        if !unknown.is_empty() {
+            problems.push(format!("template.body references unknown fields: {}", unknown.join(", ")));
+        }
+
+        if let Err(err) = Regex::new(&self.tags.pattern) {
+            problems.push(format!("tags.pattern does not compile: {}", err));
+        }
+        if let Err(err) = Regex::new(&self.jira.key_pattern) {
+            problems.push(format!("jira.key_pattern does not compile: {}", err));
+        }
+        if let Some(url) = self.jira.url.as_deref().filter(|url| !url.is_empty()) {
+            if !is_http_url(url) {
+                problems.push(format!("jira.url {} is not an http(s) url", url));
+            }
+        }
+        if !self.template.related_pr_item.contains("{{path}}") || self.template.related_pr_item.contains('\n') {
+            problems.push("template.related_pr_item must be a single line containing {{path}}".to_string());
+        }
+        if !self.template.title_format.contains("{{title}}") || self.template.title_format.contains('\n') {
+            problems.push("template.title_format must be a single line containing {{title}}".to_string());
+        }
+        if self.github.reviewer_page_size == 0 {
+            problems.push("github.reviewer_page_size must be at least 1".to_string());
+        }
+        let profiles = self.template.profile_names();
+        for rule in &self.template.profile_rules {
+            if !profiles.contains(&rule.profile) {
+                problems.push(format!("template.profile_rules references unknown profile {}", rule.profile));
+            }
+        }
+
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(Error::InvalidConfig(problems))
+        }
+    }
+
+    /// YAML of the config, with secrets redacted.
+    pub fn to_yaml(&self) -> crate::error::Result<String> {
+        let mut value = serde_yaml::to_value(self)?;
+        redact(&mut value);
+
+        Ok(serde_yaml::to_string(&value)?)
+    }
+
+    /// JSON schema of the config file, published as `git-pr.schema.json`.
+    pub fn schema() -> String {
+        let schema = schemars::schema_for!(Config);
+        serde_json::to_string_pretty(&schema).unwrap() + "\n"
+    }
+
+    /// Makes the `name` profile of `template.profiles` the template, see `--template`.
+    pub fn select_template(&mut self, name: &str) -> Result<(), String> {
+        match self.template.profiles.remove(name) {
+            Some(profile) => self.template = profile,
+            None if name == DEFAULT_TEMPLATE => {}
+            None => return Err(format!("unknown template {}, available: {}", name, self.template.profile_names().join(", "))),
+        }
+
+        Ok(())
+    }
+
+    /// Jira tickets not in `tags.ignore` get the tracking line.
+    pub fn is_tracked_jira_ticket(&self, tag: &str) -> bool {
+        self.jira.is_jira_ticket(tag) && !self.tags.is_ignored(tag)
+    }
+
+    pub fn github_user(&self) -> Option<String> {
+        self.github.user.clone().filter(|user| !user.is_empty())
+    }
+
+    /// Replaces `${VAR}` and `${VAR:-default}` in every string value.
+    fn interpolate_env(&mut self, lookup: &dyn Fn(&str) -> Option<String>) -> Result<(), ::config::ConfigError> {
+        let to_config_error = |err: serde_yaml::Error| ::config::ConfigError::Message(err.to_string());
+
+        let mut value = serde_yaml::to_value(&*self).map_err(to_config_error)?;
+        interpolate_value(&mut value, lookup).map_err(::config::ConfigError::Message)?;
+        *self = serde_yaml::from_value(value).map_err(to_config_error)?;
+
+        Ok(())
+    }
 
-pub(crate) fn get_tags_path() -> String {
+    fn apply_env_overrides(&mut self, lookup: &dyn Fn(&str) -> Option<String>) {
+        for env in ENV_OVERRIDES {
+            if let Some(value) = lookup(env.var) {
+                (env.apply)(self, value);
+            }
+        }
+    }
+
+    fn load_body_file(&mut self) -> Result<(), ::config::ConfigError> {
+        self.template.load_body_file("template")
+    }
+}
+
+fn interpolate_value(value: &mut serde_yaml::Value, lookup: &dyn Fn(&str) -> Option<String>) -> Result<(), String> {
+    match value {
+        serde_yaml::Value::String(s) => *s = interpolate(s, lookup)?,
+        serde_yaml::Value::Sequence(values) => {
+            for value in values {
+                interpolate_value(value, lookup)?;
+            }
+        }
+        serde_yaml::Value::Mapping(mapping) => {
+            for (_, value) in mapping.iter_mut() {
+                interpolate_value(value, lookup)?;
+            }
+        }
+        _ => {}
+    }
+
+    Ok(())
+}
+
+/// Expands environment references of `value`, the default is used when the variable is unset or empty.
+fn interpolate(value: &str, lookup: &dyn Fn(&str) -> Option<String>) -> Result<String, String> {
+    let mut missing: Option<String> = None;
+    let result = ENV_REFERENCE.replace_all(value, |captures: &Captures| {
+        let name = &captures[1];
+        match (lookup(name).filter(|v| !v.is_empty()), captures.get(2)) {
+            (Some(value), _) => value,
+            (None, Some(default)) => default.as_str().to_string(),
+            (None, None) => {
+                missing.get_or_insert_with(|| name.to_string());
+                String::new()
+            }
+        }
+    });
+
+    match missing {
+        Some(name) => Err(format!("environment variable {} referenced in config is not set", name)),
+        None => Ok(result.to_string()),
+    }
+}
+
+fn redact(value: &mut serde_yaml::Value) {
+    let Some(mapping) = value.as_mapping_mut() else {
+        return;
+    };
+
+    for (key, value) in mapping.iter_mut() {
+        let key = key.as_str().unwrap_or_default();
+        if SECRET_KEYS.iter().any(|secret| key.contains(secret)) && !value.is_null() {
+            *value = serde_yaml::Value::String("<redacted>".to_string());
+        } else {
+            redact(value);
+        }
+    }
+}
+
+/// `(variable, key)` of the environment overrides currently set.
+pub(crate) fn env_overrides() -> Vec<(&'static str, &'static str)> {
+    ENV_OVERRIDES.iter()
+        .filter(|env| std::env::var(env.var).is_ok())
+        .map(|env| (env.var, env.key))
+        .collect()
+}
+
+fn split_list(value: &str) -> Vec<String> {
+    value.split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .map(String::from)
+        .collect()
+}
+
+fn parse_bool(value: &str) -> bool {
+    matches!(value.trim().to_lowercase().as_str(), "1" | "true" | "yes" | "on")
+}
+
+fn is_http_url(url: &str) -> bool {
+    let Some(rest) = url.strip_prefix("https://").or_else(|| url.strip_prefix("http://")) else {
+        return false;
+    };
+    let host = rest.split('/').next().unwrap_or_default();
+
+    !host.is_empty() && !host.contains(char::is_whitespace)
+}
+
+pub(crate) fn get_config_path() -> String {
     let path = PathBuf::from(get_config_dir())
-        .join("tags.txt");
+        .join(CONFIG_FILE);
 
     path.to_str().unwrap().to_string()
 }
 
-fn get_config_dir() -> String {
+/// Drafts directory of the repository, `git_dir` is its `.git` directory.
+pub(crate) fn get_drafts_dir(git_dir: &Path) -> String {
+    let path = git_dir.join("git-pr-drafts");
+
+    ensure_config_dir_exists(path.to_str().unwrap());
+
+    path.to_str().unwrap().to_string()
+}
+
+/// Tag history file of `scope`, `git_dir` is the `.git` directory of the repository.
+pub(crate) fn get_tags_path(scope: TagsScope, git_dir: &Path) -> String {
+    let path = match scope {
+        TagsScope::Global => PathBuf::from(get_config_dir()).join("tags.txt"),
+        TagsScope::Repo => git_dir.join("git-pr-tags.txt"),
+    };
+
+    path.to_str().unwrap().to_string()
+}
+
+pub(crate) fn get_scan_cache_path() -> String {
+    let path = PathBuf::from(get_config_dir())
+        .join("branch_cache.json");
+
+    path.to_str().unwrap().to_string()
+}
+
+pub(crate) fn get_config_dir() -> String {
     if let Ok(home) = std::env::var("HOME") {
         let path = PathBuf::from(home)
             .join(".config")
@@ -28,4 +847,364 @@ fn ensure_config_dir_exists(path: &str) {
     if !path.exists() {
         std::fs::create_dir_all(path).unwrap();
     }
-}
\ No newline at end of file
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_example_config() {
+        let config: Config = ::config::Config::builder()
+            .add_source(::config::File::from_str(include_str!("../git-pr.yaml"), ::config::FileFormat::Yaml))
+            .build()
+            .unwrap()
+            .try_deserialize()
+            .unwrap();
+
+        assert_eq!(config.template.fields.len(), 2);
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn test_schema() {
+        let schema = Config::schema();
+        assert_eq!(schema, include_str!("../git-pr.schema.json"), "regenerate git-pr.schema.json with `git-pr config schema`");
+
+        let value: serde_json::Value = serde_json::from_str(&schema).unwrap();
+        let field_types: Vec<&serde_json::Value> = value["definitions"]["FieldType"]["oneOf"].as_array().unwrap()
+            .iter()
+            .map(|variant| &variant["enum"][0])
+            .collect();
+        assert_eq!(field_types, vec!["text", "editor"]);
+        assert_eq!(value["definitions"]["FormField"]["properties"]["type"]["allOf"][0]["$ref"], "#/definitions/FieldType");
+    }
+
+    #[test]
+    fn test_load_config_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("pr.conf");
+        std::fs::write(&file, "github:\n  remote: upstream\n").unwrap();
+
+        let config = Config::load(Some(&file)).unwrap();
+        assert_eq!(config.github.remote, "upstream");
+
+        assert!(Config::load(Some(&dir.path().join("missing.yaml"))).is_err());
+    }
+
+    #[test]
+    fn test_validate() {
+        assert!(Config::default().validate().is_ok());
+
+        let mut config = Config::default();
+        config.template.body = "{{description}}".to_string();
+        config.template.fields.push(config.template.fields[0].clone());
+        config.template.fields[0].pattern = Some("(".to_string());
+        config.tags.pattern = "(".to_string();
+        config.jira.url = Some("example.atlassian.net/browse/".to_string());
+        config.github.reviewer_page_size = 0;
+        config.template.profile_rules.push(ProfileRule { prefix: "BUG".to_string(), profile: "bug".to_string() });
+        config.template.title_format = "[{{tag}}]".to_string();
+
+        let Err(Error::InvalidConfig(problems)) = config.validate() else {
+            panic!("expected an invalid config");
+        };
+        assert_eq!(problems.len(), 10);
+        assert_eq!(problems[0], "template.body is missing the <!-- RELATED_PR --> marker");
+        assert_eq!(problems[1], "template.body is missing the <!-- /RELATED_PR --> marker");
+        assert!(problems[2].starts_with("pattern of field description does not compile"));
+        assert_eq!(problems[3], "field implementation is not used, add {{implementation}} to template.body");
+        assert_eq!(problems[4], "template.fields contains description more than once");
+        assert!(problems[5].starts_with("tags.pattern does not compile"));
+        assert_eq!(problems[6], "jira.url example.atlassian.net/browse/ is not an http(s) url");
+        assert_eq!(problems[7], "template.title_format must be a single line containing {{title}}");
+        assert_eq!(problems[8], "github.reviewer_page_size must be at least 1");
+        assert_eq!(problems[9], "template.profile_rules references unknown profile bug");
+    }
+
+    #[test]
+    fn test_to_yaml_redacts_secrets() {
+        let mut value: serde_yaml::Value = serde_yaml::from_str("jira:\n  url: https://example.com\n  api_token: abc\n  password: ~\n").unwrap();
+        redact(&mut value);
+
+        assert_eq!(serde_yaml::to_string(&value).unwrap(), "jira:\n  url: https://example.com\n  api_token: <redacted>\n  password: null\n");
+
+        let yaml = Config::default().to_yaml().unwrap();
+        assert!(yaml.contains("remote: origin"));
+    }
+
+    #[test]
+    fn test_unknown_placeholders() {
+        let mut template = TemplateConfig::default();
+        assert!(template.unknown_placeholders().is_empty());
+
+        template.body = "{{descripton}} on {{branch}}\n{{implementation}} {{descripton}} {{other}}".to_string();
+        assert_eq!(template.unknown_placeholders(), vec!["descripton".to_string(), "other".to_string()]);
+    }
+
+    fn lookup(name: &str) -> Option<String> {
+        match name {
+            "JIRA_BASE" => Some("https://jira.example.com".to_string()),
+            "EMPTY" => Some(String::new()),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn test_interpolate() {
+        assert_eq!(interpolate("${JIRA_BASE}/browse/", &lookup), Ok("https://jira.example.com/browse/".to_string()));
+        assert_eq!(interpolate("${MISSING:-origin}", &lookup), Ok("origin".to_string()));
+        assert_eq!(interpolate("${EMPTY:-fallback}", &lookup), Ok("fallback".to_string()));
+        assert_eq!(interpolate("${MISSING:-}", &lookup), Ok(String::new()));
+        assert_eq!(interpolate("$JIRA_BASE {{tag}}", &lookup), Ok("$JIRA_BASE {{tag}}".to_string()));
+        assert!(interpolate("${MISSING}", &lookup).is_err());
+    }
+
+    #[test]
+    fn test_interpolate_env() {
+        let mut config = Config::default();
+        config.jira.url = Some("${JIRA_BASE}/browse/".to_string());
+        config.github.default_reviewers = vec!["${REVIEWER:-alice}".to_string()];
+
+        config.interpolate_env(&lookup).unwrap();
+
+        assert_eq!(config.jira.url.as_deref(), Some("https://jira.example.com/browse/"));
+        assert_eq!(config.github.default_reviewers, vec!["alice".to_string()]);
+        assert_eq!(config.template.body, template::TEMPLATE);
+
+        config.github.remote = "${MISSING}".to_string();
+        assert!(config.interpolate_env(&lookup).is_err());
+    }
+
+    #[test]
+    fn test_apply_env_overrides() {
+        let mut config = Config::default();
+        config.github.default_reviewers = vec!["carol".to_string()];
+
+        config.apply_env_overrides(&|name| match name {
+            "GIT_PR_DEFAULT_BASE" => Some("develop".to_string()),
+            "GIT_PR_DEFAULT_REVIEWERS" => Some("alice, bob,".to_string()),
+            "GIT_PR_DRAFT" => Some("true".to_string()),
+            "GITHUB_USER" => Some("octocat".to_string()),
+            _ => None,
+        });
+
+        assert_eq!(config.github.default_base.as_deref(), Some("develop"));
+        assert_eq!(config.github.default_reviewers, vec!["alice".to_string(), "bob".to_string()]);
+        assert!(config.github.draft);
+        assert_eq!(config.github_user().as_deref(), Some("octocat"));
+        assert_eq!(config.jira.url, None);
+    }
+
+    #[test]
+    fn test_load_body_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("body.md");
+        std::fs::write(&path, "{{description}}").unwrap();
+
+        let mut config = Config::default();
+        config.template.body_file = Some(path.to_str().unwrap().to_string());
+        config.load_body_file().unwrap();
+        assert_eq!(config.template.body, "{{description}}");
+
+        config.template.body_file = Some(dir.path().join("missing.md").to_str().unwrap().to_string());
+        assert!(config.load_body_file().is_err());
+
+        config.template.body_file = None;
+        let profile = TemplateConfig { body_file: Some(path.to_str().unwrap().to_string()), ..Default::default() };
+        config.template.profiles.insert("hotfix".to_string(), profile);
+        config.load_body_file().unwrap();
+        assert_eq!(config.template.profiles["hotfix"].body, "{{description}}");
+    }
+
+    #[test]
+    fn test_get_tags_path() {
+        let git_dir = Path::new("/work/project/.git");
+
+        assert_eq!(get_tags_path(TagsScope::Repo, git_dir), "/work/project/.git/git-pr-tags.txt");
+        assert!(get_tags_path(TagsScope::Global, git_dir).ends_with("git-pr/tags.txt"));
+    }
+
+    #[test]
+    fn test_get_drafts_dir() {
+        let (first, second) = (tempfile::tempdir().unwrap(), tempfile::tempdir().unwrap());
+
+        let drafts = get_drafts_dir(first.path());
+        assert_eq!(drafts, first.path().join("git-pr-drafts").to_str().unwrap());
+        assert!(Path::new(&drafts).is_dir());
+        assert_ne!(drafts, get_drafts_dir(second.path()));
+    }
+
+    #[test]
+    fn test_select_template() {
+        let yaml = r#"
+template:
+  body: "{{description}}"
+  profiles:
+    hotfix:
+      body: "{{fix}}"
+      fields:
+        - name: fix
+          message: 'What is broken: '
+"#;
+        let config: Config = serde_yaml::from_str(yaml).unwrap();
+        assert_eq!(config.template.profile_names(), vec!["default", "hotfix"]);
+
+        let mut default = config.clone();
+        default.select_template(DEFAULT_TEMPLATE).unwrap();
+        assert_eq!(default.template.body, "{{description}}");
+
+        let mut hotfix = config.clone();
+        hotfix.select_template("hotfix").unwrap();
+        assert_eq!(hotfix.template.body, "{{fix}}");
+        assert_eq!(hotfix.template.fields.len(), 1);
+        assert_eq!(hotfix.template.fields[0].name, "fix");
+        assert!(hotfix.template.profiles.is_empty());
+
+        let mut unknown = config.clone();
+        assert_eq!(unknown.select_template("feature").unwrap_err(), "unknown template feature, available: default, hotfix");
+
+        let mut listed = config;
+        listed.template.profiles.insert(DEFAULT_TEMPLATE.to_string(), TemplateConfig { body: "listed".to_string(), ..Default::default() });
+        listed.select_template(DEFAULT_TEMPLATE).unwrap();
+        assert_eq!(listed.template.body, "listed");
+    }
+
+    #[test]
+    fn test_profile_for_tag() {
+        let template = TemplateConfig {
+            profile_rules: vec![
+                ProfileRule { prefix: "BUG".to_string(), profile: "bug".to_string() },
+                ProfileRule { prefix: "feat".to_string(), profile: "feature".to_string() },
+            ],
+            ..Default::default()
+        };
+
+        assert_eq!(template.profile_for_tag("BUG-12"), Some("bug"));
+        assert_eq!(template.profile_for_tag("FEAT-3"), Some("feature"));
+        assert_eq!(template.profile_for_tag("BUGFIX-1"), None);
+        assert_eq!(template.profile_for_tag("TRACK-123"), None);
+    }
+
+    #[test]
+    fn test_is_http_url() {
+        assert!(is_http_url("https://example.atlassian.net/browse/"));
+        assert!(is_http_url("http://jira.local"));
+        assert!(!is_http_url("example.atlassian.net"));
+        assert!(!is_http_url("https:///browse"));
+    }
+
+    #[test]
+    fn test_is_jira_ticket() {
+        let jira = JiraConfig {
+            url: Some("https://example.atlassian.net/browse/".to_string()),
+            ..Default::default()
+        };
+
+        assert!(jira.is_jira_ticket("TRACK-123"));
+        assert!(!jira.is_jira_ticket("CHORE"));
+        assert!(!jira.is_jira_ticket("track-123"));
+    }
+
+    #[test]
+    fn test_jira_link() {
+        let mut jira = JiraConfig {
+            url: Some("https://example.atlassian.net/browse/".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(jira.link("TRACK-1"), "https://example.atlassian.net/browse/TRACK-1");
+
+        jira.url = Some("https://example.atlassian.net/browse".to_string());
+        assert_eq!(jira.link("TRACK-1"), "https://example.atlassian.net/browse/TRACK-1");
+
+        jira.url = Some("https://jira.example.com/".to_string());
+        jira.link_template = Some("{{url}}/browse/{{tag}}".to_string());
+        assert_eq!(jira.link("TRACK-1"), "https://jira.example.com/browse/TRACK-1");
+
+        jira.url = Some("https://jira.example.com".to_string());
+        assert_eq!(jira.link("TRACK-1"), "https://jira.example.com/browse/TRACK-1");
+    }
+
+    #[test]
+    fn test_split_command() {
+        assert_eq!(split_command("code --wait"), Some(("code".to_string(), vec!["--wait".to_string()])));
+        assert_eq!(split_command("vim"), Some(("vim".to_string(), vec![])));
+        assert_eq!(split_command("  "), None);
+    }
+
+    fn text_field() -> FormField {
+        FormField {
+            name: "summary".to_string(),
+            message: "Summary:".to_string(),
+            field_type: FieldType::Text,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_field_required() {
+        let field = FormField { required: true, ..text_field() };
+
+        assert!(field.validate("  ").is_err());
+        assert!(field.validate("value").is_ok());
+        assert!(text_field().validate("").is_ok());
+    }
+
+    #[test]
+    fn test_field_min_length() {
+        let field = FormField { min_length: Some(5), ..text_field() };
+
+        assert!(field.validate("abcd").is_err());
+        assert!(field.validate("abcde").is_ok());
+    }
+
+    #[test]
+    fn test_field_max_length() {
+        let field = FormField { max_length: Some(5), ..text_field() };
+
+        assert!(field.validate("abcdef").is_err());
+        assert!(field.validate("abcde").is_ok());
+    }
+
+    #[test]
+    fn test_field_pattern() {
+        let field = FormField { pattern: Some(r"^v\d+\.\d+$".to_string()), ..text_field() };
+
+        assert!(field.validate("version 1").is_err());
+        assert!(field.validate("v1.2").is_ok());
+
+        let field = FormField { pattern: Some("(".to_string()), ..text_field() };
+        assert!(field.validate("anything").is_err());
+    }
+
+    #[test]
+    fn test_tags_validate() {
+        let tags = TagsConfig::default();
+
+        assert!(tags.validate("TRACK-123").is_ok());
+        assert!(tags.validate("CHORE").is_ok());
+        assert!(tags.validate("[TRACK-123]").is_err());
+        assert!(tags.validate("TRACK 123").is_err());
+        assert!(TagsConfig { pattern: "(".to_string(), ..Default::default() }.validate("TRACK-1").is_err());
+    }
+
+    #[test]
+    fn test_is_tracked_jira_ticket() {
+        let mut config = Config::default();
+        config.jira.url = Some("https://example.atlassian.net/browse/".to_string());
+        config.tags.ignore = vec!["wip-1".to_string()];
+
+        assert!(config.is_tracked_jira_ticket("TRACK-1"));
+        assert!(!config.is_tracked_jira_ticket("WIP-1"));
+        assert!(config.tags.is_ignored("WIP-1"));
+        assert!(!config.tags.is_ignored("TRACK-1"));
+    }
+
+    #[test]
+    fn test_is_jira_ticket_without_url() {
+        let jira = JiraConfig::default();
+
+        assert!(!jira.is_jira_ticket("TRACK-123"));
+    }
+}
@@ -0,0 +1,101 @@
+use inquire::error::InquireError;
+use thiserror::Error;
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("expected to be run in git repository")]
+    NotInGitRepo,
+
+    #[error("branch is not clean")]
+    BranchNotClean,
+
+    #[error("can't be in main branch: {0}")]
+    CannotBeInMainBranch(String),
+
+    #[error("no commits found")]
+    NoCommits,
+
+    #[error("HEAD is detached")]
+    DetachedHead,
+
+    #[error("branch {0} is checked out in another worktree: {1}")]
+    CheckedOutElsewhere(String, String),
+
+    #[error("operation cancelled")]
+    Cancelled,
+
+    #[error("invalid input: {0}")]
+    InvalidInput(String),
+
+    #[error("gh failed: {0}")]
+    GitHubCli(String),
+
+    #[error("glab failed: {0}")]
+    GitLabCli(String),
+
+    #[error("prompt failed: {0}")]
+    Prompt(String),
+
+    #[error("config error: {0}")]
+    Config(#[from] ::config::ConfigError),
+
+    #[error("invalid config: {}", .0.join(", "))]
+    InvalidConfig(Vec<String>),
+
+    #[error("git error: {0}")]
+    Git(#[from] git2::Error),
+
+    #[error("io error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("json error: {0}")]
+    Json(#[from] serde_json::Error),
+
+    #[error("yaml error: {0}")]
+    Yaml(#[from] serde_yaml::Error),
+}
+
+impl Error {
+    /// Exit code of the binary, `130` follows the SIGINT convention for an aborted prompt.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Error::Cancelled => 130,
+            Error::NotInGitRepo
+            | Error::BranchNotClean
+            | Error::CannotBeInMainBranch(_)
+            | Error::NoCommits
+            | Error::DetachedHead
+            | Error::CheckedOutElsewhere(_, _)
+            | Error::Git(_) => 3,
+            Error::GitHubCli(_) | Error::GitLabCli(_) | Error::Json(_) => 4,
+            Error::Io(_) => 74,
+            Error::Config(_) | Error::InvalidConfig(_) | Error::Yaml(_) => 78,
+            Error::InvalidInput(_) | Error::Prompt(_) => 1,
+        }
+    }
+}
+
+pub(crate) fn map_inquire_error(err: InquireError) -> Error {
+    match err {
+        InquireError::OperationCanceled | InquireError::OperationInterrupted => Error::Cancelled,
+        InquireError::IO(err) => Error::Io(err),
+        err => Error::Prompt(err.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exit_code() {
+        assert_eq!(map_inquire_error(InquireError::OperationInterrupted).exit_code(), 130);
+        assert_eq!(map_inquire_error(InquireError::OperationCanceled).exit_code(), 130);
+        assert_eq!(Error::InvalidInput("bad".into()).exit_code(), 1);
+        assert_eq!(Error::NotInGitRepo.exit_code(), 3);
+        assert_eq!(Error::GitHubCli("bad".into()).exit_code(), 4);
+        assert_eq!(Error::GitLabCli("bad".into()).exit_code(), 4);
+    }
+}
@@ -1,6 +0,0 @@
-#[derive(Debug)]
-pub enum Error {
-    NotInGitRepo,
-    BranchNotClean,
-    CannotBeInMainBranch(String),
-}
\ No newline at end of file
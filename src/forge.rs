@@ -0,0 +1,68 @@
+use std::path::PathBuf;
+
+use crate::config::{Config, ForgeKind};
+use crate::error::{Error, Result};
+use crate::github::{GhClient, PullRequest, Reviewer};
+use crate::gitlab::GlabClient;
+
+/// PR opened by [`Forge::publish_pr`].
+#[derive(Debug, Default, Clone, Copy)]
+pub(crate) struct NewPr<'a> {
+    /// `owner/name` the PR is opened against, inferred by the CLI of the forge when missing.
+    pub repo: Option<&'a str>,
+    pub base: &'a str,
+    pub title: &'a str,
+    pub body: &'a str,
+    pub reviewers: &'a [String],
+    /// Opens the compose page in the browser, pre-filled, instead of creating the PR.
+    pub web: bool,
+}
+
+/// Edit of an existing PR by [`Forge::update_pr`], `None` values are left as they are.
+#[derive(Debug, Default, Clone, Copy)]
+pub(crate) struct PrUpdate<'a> {
+    pub number: u32,
+    /// Path of the PR url, eg. `/owner/repo/pull/12`, it names the repository of the PR.
+    pub resource_path: &'a str,
+    pub title: Option<&'a str>,
+    pub body: Option<&'a str>,
+}
+
+/// Forge operations of the PR flow, implemented by [`GhClient`] and [`GlabClient`].
+/// GitLab merge requests are PRs here, numbered by their `iid`.
+pub(crate) trait Forge {
+    fn publish_pr(&self, config: &Config, pr: &NewPr) -> Result<String>;
+    fn update_pr(&self, update: &PrUpdate) -> Result<String>;
+    fn get_user_prs(&self, login: &str) -> Result<Vec<PullRequest>>;
+    /// Assignable users of `repo`, bot accounts are left out unless `include_bots`.
+    fn get_available_reviewers(&self, repo: Option<&str>, include_bots: bool) -> Result<Vec<Reviewer>>;
+    fn get_authenticated_user(&self) -> Result<String>;
+    /// Open PR whose head is `branch`, `None` when there is none.
+    fn get_pr_by_branch(&self, repo: Option<&str>, branch: &str) -> Result<Option<PullRequest>>;
+    fn get_pr_by_number(&self, repo: Option<&str>, number: u32) -> Result<PullRequest>;
+    fn open_in_browser(&self, url: &str) -> Result<()>;
+}
+
+/// Client of `forge`, see [`GhClient`] for `dry_run` and `out`.
+pub(crate) fn client(config: &Config, dry_run: bool, out: Option<PathBuf>) -> Result<Box<dyn Forge>> {
+    match config.forge {
+        ForgeKind::GitHub => Ok(Box::new(GhClient { dry_run, out })),
+        ForgeKind::GitLab if out.is_some() => Err(Error::InvalidInput("--out is not supported with forge gitlab".into())),
+        ForgeKind::GitLab => Ok(Box::new(GlabClient { dry_run })),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_client() {
+        let config: Config = serde_yaml::from_str("forge: gitlab\n").unwrap();
+
+        assert_eq!(config.forge, ForgeKind::GitLab);
+        assert!(client(&config, true, None).is_ok());
+        assert!(matches!(client(&config, true, Some(PathBuf::from("pr.md"))), Err(Error::InvalidInput(_))));
+        assert!(client(&Config::default(), true, Some(PathBuf::from("pr.md"))).is_ok());
+    }
+}
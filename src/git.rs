@@ -1,10 +1,16 @@
+use std::collections::hash_map::DefaultHasher;
 use std::collections::{HashMap, HashSet};
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
 
 use git2::{BranchType, Oid, Repository, RepositoryState};
 use inquire::{Autocomplete, CustomUserError};
 use inquire::autocompletion::Replacement;
+use serde::{Deserialize, Serialize};
+use tracing::{debug, instrument};
 
-use crate::errors::Error;
+use crate::config::GitConfig;
+use crate::error::Error;
 
 pub(crate) fn get_repository() -> Result<Repository, Error> {
     let r = Repository::open(".").map_err(|_| Error::NotInGitRepo)?;
@@ -15,10 +21,14 @@ pub(crate) fn get_repository() -> Result<Repository, Error> {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct BranchInfo {
+    pub branch: String,
     pub bases: Vec<String>,
+    /// Subject lines of the branch commits, newest first.
     pub commits: Vec<String>,
+    /// Full messages of `commits`.
+    pub messages: Vec<String>,
 }
 
 impl Autocomplete for BranchInfo {
@@ -33,8 +43,8 @@ impl Autocomplete for BranchInfo {
     }
 
     fn get_completion(&mut self, input: &str, highlighted_suggestion: Option<String>) -> Result<Replacement, CustomUserError> {
-        if let Some(..) = highlighted_suggestion {
-            return Ok(Some(highlighted_suggestion.unwrap()));
+        if let Some(suggestion) = highlighted_suggestion {
+            return Ok(Some(suggestion));
         }
         for tag in self.commits.iter() {
             if tag.contains(input) {
@@ -45,49 +55,132 @@ impl Autocomplete for BranchInfo {
     }
 }
 
+/// Choices of the PR base prompt, `detected` bases are suggested before the `known` branches.
+#[derive(Debug, Default, Clone)]
+pub struct BaseCandidates {
+    pub detected: Vec<String>,
+    /// Every local and remote branch name, any of them is accepted as the base.
+    pub known: Vec<String>,
+}
 
-pub(crate) fn get_branch_bases_and_commits() -> Result<BranchInfo, Error> {
-    let repo = get_repository()?;
+impl BaseCandidates {
+    pub fn is_known(&self, name: &str) -> bool {
+        self.detected.iter().chain(self.known.iter()).any(|b| b == name)
+    }
+}
 
-    let head = repo.head().map_err(|_| Error::BranchNotClean)?;
-    let current_branch = head.shorthand().unwrap_or("HEAD");
+impl Autocomplete for BaseCandidates {
+    fn get_suggestions(&mut self, input: &str) -> Result<Vec<String>, CustomUserError> {
+        let input = input.to_lowercase();
+        let others = self.known.iter().filter(|b| !self.detected.contains(b));
+        Ok(self.detected.iter()
+            .chain(others)
+            .filter(|b| b.to_lowercase().contains(input.as_str()))
+            .cloned()
+            .collect())
+    }
 
+    fn get_completion(&mut self, input: &str, highlighted_suggestion: Option<String>) -> Result<Replacement, CustomUserError> {
+        if let Some(suggestion) = highlighted_suggestion {
+            return Ok(Some(suggestion));
+        }
+        Ok(self.get_suggestions(input)?.into_iter().next())
+    }
+}
+
+/// Result of the last branch scan of a repository, see [`get_branch_bases_and_commits`].
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct ScanCache {
+    /// Repository path to its last scan.
+    repositories: HashMap<String, CachedScan>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct CachedScan {
+    key: String,
+    info: BranchInfo,
+}
+
+/// Scans branches of the current repository, `cache` is the file scan results
+/// are reused from as long as HEAD and the branches didn't move.
+#[instrument(skip_all)]
+pub(crate) fn get_branch_bases_and_commits(config: &GitConfig, cache: Option<&Path>) -> Result<BranchInfo, Error> {
+    let repo = get_repository()?;
+
+    let current_branch = get_current_branch(&repo)?;
+    let current_branch = current_branch.as_str();
     if is_main(current_branch) {
         return Err(Error::CannotBeInMainBranch(current_branch.to_string()));
     }
+    if let Some(path) = checked_out_elsewhere(&repo, current_branch)? {
+        return Err(Error::CheckedOutElsewhere(current_branch.to_string(), path.display().to_string()));
+    }
 
-    let mut commit_branches: HashMap<Oid, HashSet<String>> = HashMap::new();
-    let branches = repo.branches(None).unwrap();
+    match cache {
+        Some(cache) => scan_cached(&repo, config, current_branch, cache),
+        None => scan_branches(&repo, config, current_branch),
+    }
+}
 
-    for result in branches {
-        let (branch, _) = result.unwrap();
+fn scan_cached(repo: &Repository, config: &GitConfig, current_branch: &str, cache_path: &Path) -> Result<BranchInfo, Error> {
+    let repo_path = repo.path().to_string_lossy().to_string();
+    let key = scan_key(repo, config)?;
 
-        let name = branch.get().shorthand().unwrap();
-        if name == current_branch || name == format!("origin/{}", current_branch) {
-            continue;
+    let mut cache: ScanCache = std::fs::read(cache_path).ok()
+        .and_then(|data| serde_json::from_slice(&data).ok())
+        .unwrap_or_default();
+    if let Some(cached) = cache.repositories.get(&repo_path) {
+        if cached.key == key {
+            debug!(%key, "branch scan cache hit");
+            return Ok(cached.info.clone());
         }
+    }
 
-        let mut revwalk = repo.revwalk().unwrap();
-        revwalk.push_ref(branch.get().name().unwrap()).unwrap();
+    let info = scan_branches(repo, config, current_branch)?;
+    cache.repositories.insert(repo_path, CachedScan { key, info: info.clone() });
+    let saved = serde_json::to_vec(&cache)
+        .map_err(std::io::Error::from)
+        .and_then(|data| std::fs::write(cache_path, data));
+    if let Err(err) = saved {
+        debug!(%err, "unable to save branch scan cache");
+    }
 
-        for each in revwalk {
-            let id = each.unwrap();
+    Ok(info)
+}
 
-            commit_branches.entry(id).and_modify(|curr| {
-                curr.insert(name.into());
-            }).or_insert_with(|| HashSet::from([name.into()]));
-        }
+/// HEAD oid followed by a hash of every branch name and target and of the scan settings.
+fn scan_key(repo: &Repository, config: &GitConfig) -> Result<String, Error> {
+    let head = repo.head()?.target().map(|oid| oid.to_string()).unwrap_or_default();
+
+    let mut branches: Vec<(String, String)> = Vec::new();
+    for result in repo.branches(None)? {
+        let (branch, _) = result?;
+        let reference = branch.get();
+        let target = reference.target().map(|oid| oid.to_string()).unwrap_or_default();
+        branches.push((reference.name().unwrap_or_default().to_string(), target));
     }
+    branches.sort();
+
+    let mut hasher = DefaultHasher::new();
+    branches.hash(&mut hasher);
+    config.hash(&mut hasher);
+
+    Ok(format!("{}:{:x}", head, hasher.finish()))
+}
 
-    let branch = repo.find_branch(current_branch, BranchType::Local).unwrap();
-    let mut revwalk = repo.revwalk().unwrap();
-    revwalk.push_ref(branch.get().name().unwrap()).unwrap();
+fn scan_branches(repo: &Repository, config: &GitConfig, current_branch: &str) -> Result<BranchInfo, Error> {
+    let commit_branches = index_branches(repo, current_branch, config.base_scan_limit)?;
+
+    let branch = repo.find_branch(current_branch, BranchType::Local)?;
+    let mut revwalk = repo.revwalk()?;
+    revwalk.push_ref(branch.get().name().ok_or(Error::BranchNotClean)?)?;
 
     let mut bases: Vec<String> = Vec::new();
     let mut commits: Vec<String> = Vec::new();
+    let mut messages: Vec<String> = Vec::new();
 
     for each in revwalk {
-        let oid = each.unwrap();
+        let oid = each?;
 
         if let Some(branches) = commit_branches.get(&oid) {
             let mut branches = branches.iter().collect::<Vec<&String>>();
@@ -97,20 +190,428 @@ pub(crate) fn get_branch_bases_and_commits() -> Result<BranchInfo, Error> {
             });
             break;
         } else {
-            let commit = repo.find_commit(oid).unwrap();
-            let message = commit.message().unwrap();
-            commits.push(message.trim().to_string());
+            let commit = repo.find_commit(oid)?;
+            if config.skip_merge_commits && commit.parent_count() > 1 {
+                continue;
+            }
+            let message = commit.message().unwrap_or_default().trim();
+            commits.push(message.lines().next().unwrap_or_default().trim().to_string());
+            messages.push(message.to_string());
         }
     }
 
+    debug!(branch = current_branch, ?bases, commits = commits.len(), "branch scanned");
+
     Ok(BranchInfo {
+        branch: current_branch.to_string(),
         bases,
         commits,
+        messages,
     })
 }
 
+/// Maps commits to the branches containing them, other than the current branch.
+/// With `limit` only the last `limit` commits of every branch are indexed.
+fn index_branches(repo: &Repository, current_branch: &str, limit: Option<usize>) -> Result<HashMap<Oid, HashSet<String>>, Error> {
+    let mut commit_branches: HashMap<Oid, HashSet<String>> = HashMap::new();
+    let branches = repo.branches(None)?;
+
+    for result in branches {
+        let (branch, _) = result?;
+
+        let (Some(name), Some(ref_name)) = (branch.get().shorthand(), branch.get().name()) else {
+            continue;
+        };
+        if name == current_branch || name == format!("origin/{}", current_branch) {
+            continue;
+        }
+        debug!(branch = name, "scanning branch");
+
+        let mut revwalk = repo.revwalk()?;
+        revwalk.push_ref(ref_name)?;
+
+        for each in revwalk.take(limit.unwrap_or(usize::MAX)) {
+            let id = each?;
+
+            commit_branches.entry(id).and_modify(|curr| {
+                curr.insert(name.into());
+            }).or_insert_with(|| HashSet::from([name.into()]));
+        }
+    }
+
+    Ok(commit_branches)
+}
+
+/// `.git` directory shared by the worktrees of `repo`.
+pub(crate) fn common_dir(repo: &Repository) -> &Path {
+    // Linked worktrees live in `<common dir>/worktrees/<name>`.
+    if repo.is_worktree() {
+        repo.path().parent().and_then(Path::parent).unwrap_or(repo.path())
+    } else {
+        repo.path()
+    }
+}
+
+/// Working tree, other than the one of `repo`, having `branch` checked out.
+/// HEAD of `repo` is specific to its worktree, but branches are shared by all of them.
+pub(crate) fn checked_out_elsewhere(repo: &Repository, branch: &str) -> Result<Option<PathBuf>, Error> {
+    let common_dir = common_dir(repo);
+
+    let mut others = vec![Repository::open(common_dir)?];
+    for name in repo.worktrees()?.iter().flatten() {
+        let worktree = repo.find_worktree(name)?;
+        if worktree.validate().is_err() {
+            continue;
+        }
+        others.push(Repository::open_from_worktree(&worktree)?);
+    }
+
+    for other in others {
+        if other.path() == repo.path() || other.is_bare() {
+            continue;
+        }
+        let Ok(head) = other.head() else {
+            continue;
+        };
+        if head.is_branch() && head.shorthand() == Some(branch) {
+            return Ok(other.workdir().map(Path::to_path_buf));
+        }
+    }
+
+    Ok(None)
+}
+
+pub(crate) fn get_current_branch(repo: &Repository) -> Result<String, Error> {
+    let head = repo.head().map_err(|_| Error::BranchNotClean)?;
+    if !head.is_branch() {
+        return Err(Error::DetachedHead);
+    }
+
+    Ok(head.shorthand().unwrap_or("HEAD").to_string())
+}
+
 fn is_main(name: &str) -> bool {
-    let forbidden = vec!["master", "main", "development", "stage", "production"];
+    let forbidden = ["master", "main", "development", "stage", "production"];
     forbidden.contains(&name)
 }
 
+pub(crate) fn base_exists_on_remote(repo: &Repository, remote: &str, base: &str) -> bool {
+    repo.find_branch(format!("{}/{}", remote, base).as_str(), BranchType::Remote).is_ok()
+}
+
+pub(crate) fn branch_exists(repo: &Repository, remote: &str, name: &str) -> bool {
+    repo.find_branch(name, BranchType::Local).is_ok() || base_exists_on_remote(repo, remote, name)
+}
+
+/// Names of the local branches and of the branches of `remote`, without the remote prefix.
+pub(crate) fn branch_names(repo: &Repository, remote: &str) -> Result<Vec<String>, Error> {
+    let prefix = format!("{}/", remote);
+    let mut names = Vec::new();
+    for result in repo.branches(None)? {
+        let (branch, branch_type) = result?;
+        let Some(name) = branch.name()? else {
+            continue;
+        };
+        let name = match branch_type {
+            BranchType::Local => name,
+            BranchType::Remote => match name.strip_prefix(prefix.as_str()) {
+                Some(name) if name != "HEAD" => name,
+                _ => continue,
+            },
+        };
+        names.push(name.to_string());
+    }
+    names.sort();
+    names.dedup();
+
+    Ok(names)
+}
+
+/// Paths changed on HEAD since it forked from `base`, the local branch or the one of `remote`.
+pub(crate) fn changed_files(repo: &Repository, remote: &str, base: &str) -> Result<Vec<String>, Error> {
+    let base_branch = repo.find_branch(base, BranchType::Local)
+        .or_else(|_| repo.find_branch(format!("{}/{}", remote, base).as_str(), BranchType::Remote))?;
+    let base_oid = base_branch.get().peel_to_commit()?.id();
+    let head = repo.head()?.peel_to_commit()?;
+
+    let fork_point = repo.find_commit(repo.merge_base(base_oid, head.id())?)?;
+    let diff = repo.diff_tree_to_tree(Some(&fork_point.tree()?), Some(&head.tree()?), None)?;
+
+    Ok(diff.deltas()
+        .filter_map(|delta| delta.new_file().path().or_else(|| delta.old_file().path()).map(|p| p.to_string_lossy().to_string()))
+        .collect())
+}
+
+pub(crate) fn get_remote_repo(repo: &Repository, remote: &str) -> Option<String> {
+    let remote = repo.find_remote(remote).ok()?;
+    parse_remote_url(remote.url()?)
+}
+
+/// Extracts `owner/name` from ssh (`git@host:owner/name.git`) and http(s) remote urls.
+pub(crate) fn parse_remote_url(url: &str) -> Option<String> {
+    let url = url.trim_end_matches('/');
+    let url = url.strip_suffix(".git").unwrap_or(url);
+
+    let parts: Vec<&str> = url.split(['/', ':']).filter(|p| !p.is_empty()).collect();
+    if parts.len() < 3 {
+        return None;
+    }
+
+    Some(parts[parts.len() - 2..].join("/"))
+}
+
+pub(crate) fn get_remote_default_branch(repo: &Repository, remote: &str) -> Option<String> {
+    let head = repo.find_reference(format!("refs/remotes/{}/HEAD", remote).as_str()).ok()?;
+    let target = head.symbolic_target()?;
+
+    target.strip_prefix(format!("refs/remotes/{}/", remote).as_str()).map(String::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture() -> (tempfile::TempDir, Repository) {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        {
+            let sig = git2::Signature::now("test", "test@example.com").unwrap();
+            let tree_id = repo.index().unwrap().write_tree().unwrap();
+            let tree = repo.find_tree(tree_id).unwrap();
+            let oid = repo.commit(Some("HEAD"), &sig, &sig, "initial", &tree, &[]).unwrap();
+
+            repo.reference("refs/remotes/origin/main", oid, true, "fixture").unwrap();
+            repo.reference("refs/remotes/origin/develop", oid, true, "fixture").unwrap();
+            repo.reference_symbolic("refs/remotes/origin/HEAD", "refs/remotes/origin/main", true, "fixture").unwrap();
+            repo.remote("origin", "git@github.com:me/git-pr.git").unwrap();
+            repo.remote("upstream", "https://github.com/IAmRadek/git-pr").unwrap();
+        }
+        (dir, repo)
+    }
+
+    #[test]
+    fn test_base_exists_on_remote() {
+        let (_dir, repo) = fixture();
+
+        assert!(base_exists_on_remote(&repo, "origin", "main"));
+        assert!(base_exists_on_remote(&repo, "origin", "develop"));
+        assert!(!base_exists_on_remote(&repo, "origin", "merged-feature"));
+        assert!(!base_exists_on_remote(&repo, "upstream", "main"));
+    }
+
+    #[test]
+    fn test_branch_exists() {
+        let (_dir, repo) = fixture();
+        let head = repo.head().unwrap().peel_to_commit().unwrap();
+        repo.branch("local-only", &head, false).unwrap();
+
+        assert!(branch_exists(&repo, "origin", "local-only"));
+        assert!(branch_exists(&repo, "origin", "develop"));
+        assert!(!branch_exists(&repo, "origin", "missing"));
+    }
+
+    #[test]
+    fn test_branch_names() {
+        let (_dir, repo) = fixture();
+        let head = repo.head().unwrap().peel_to_commit().unwrap();
+        repo.branch("develop", &head, false).unwrap();
+        repo.branch("local-only", &head, false).unwrap();
+
+        let initial = repo.head().unwrap().shorthand().unwrap().to_string();
+
+        let mut expected = vec!["develop".to_string(), "local-only".to_string(), "main".to_string(), initial.clone()];
+        expected.sort();
+        expected.dedup();
+        assert_eq!(branch_names(&repo, "origin").unwrap(), expected);
+        // Branches of other remotes are left out.
+        assert_eq!(branch_names(&repo, "upstream").unwrap().contains(&"main".to_string()), initial == "main");
+    }
+
+    #[test]
+    fn test_base_candidates() {
+        let mut candidates = BaseCandidates {
+            detected: vec!["develop".to_string(), "main".to_string()],
+            known: vec!["develop".to_string(), "main".to_string(), "release/1.0".to_string(), "Release/2.0".to_string()],
+        };
+
+        assert_eq!(candidates.get_suggestions("").unwrap(), vec!["develop", "main", "release/1.0", "Release/2.0"]);
+        assert_eq!(candidates.get_suggestions("rel").unwrap(), vec!["release/1.0", "Release/2.0"]);
+        assert_eq!(candidates.get_completion("2.0", None).unwrap(), Some("Release/2.0".to_string()));
+        assert!(candidates.is_known("release/1.0"));
+        assert!(!candidates.is_known("missing"));
+    }
+
+    #[test]
+    fn test_get_remote_default_branch() {
+        let (_dir, repo) = fixture();
+
+        assert_eq!(get_remote_default_branch(&repo, "origin"), Some("main".to_string()));
+        assert_eq!(get_remote_default_branch(&repo, "upstream"), None);
+    }
+
+    #[test]
+    fn test_parse_remote_url() {
+        assert_eq!(parse_remote_url("git@github.com:owner/repo.git"), Some("owner/repo".to_string()));
+        assert_eq!(parse_remote_url("https://github.com/owner/repo"), Some("owner/repo".to_string()));
+        assert_eq!(parse_remote_url("https://github.com/owner/repo.git/"), Some("owner/repo".to_string()));
+        assert_eq!(parse_remote_url("ssh://git@github.com/owner/repo.git"), Some("owner/repo".to_string()));
+        assert_eq!(parse_remote_url("repo"), None);
+    }
+
+    #[test]
+    fn test_get_remote_repo() {
+        let (_dir, repo) = fixture();
+
+        assert_eq!(get_remote_repo(&repo, "origin"), Some("me/git-pr".to_string()));
+        assert_eq!(get_remote_repo(&repo, "upstream"), Some("IAmRadek/git-pr".to_string()));
+        assert_eq!(get_remote_repo(&repo, "missing"), None);
+    }
+
+    fn checkout_feature(repo: &Repository) {
+        let sig = git2::Signature::now("test", "test@example.com").unwrap();
+        let parent = repo.head().unwrap().peel_to_commit().unwrap();
+        repo.branch("feature", &parent, false).unwrap();
+        repo.set_head("refs/heads/feature").unwrap();
+        repo.commit(Some("HEAD"), &sig, &sig, "[TRACK-1]: feature", &parent.tree().unwrap(), &[&parent]).unwrap();
+    }
+
+    #[test]
+    fn test_changed_files() {
+        let (_dir, repo) = fixture();
+        let base = repo.head().unwrap().shorthand().unwrap().to_string();
+        checkout_feature(&repo);
+        {
+            let sig = git2::Signature::now("test", "test@example.com").unwrap();
+            let parent = repo.head().unwrap().peel_to_commit().unwrap();
+            let mut builder = repo.treebuilder(Some(&parent.tree().unwrap())).unwrap();
+            builder.insert("added.rs", repo.blob(b"fn main() {}").unwrap(), 0o100644).unwrap();
+            let tree = repo.find_tree(builder.write().unwrap()).unwrap();
+            repo.commit(Some("HEAD"), &sig, &sig, "add file", &tree, &[&parent]).unwrap();
+        }
+
+        assert_eq!(changed_files(&repo, "origin", &base).unwrap(), vec!["added.rs"]);
+        assert_eq!(changed_files(&repo, "origin", "develop").unwrap(), vec!["added.rs"]);
+        assert!(changed_files(&repo, "origin", "missing").is_err());
+    }
+
+    #[test]
+    fn test_scan_cached() {
+        let (dir, repo) = fixture();
+        checkout_feature(&repo);
+        let cache_path = dir.path().join("branch_cache.json");
+
+        let info = scan_cached(&repo, &GitConfig::default(), "feature", &cache_path).unwrap();
+        assert_eq!(info, scan_branches(&repo, &GitConfig::default(), "feature").unwrap());
+        assert_eq!(info.commits, vec!["[TRACK-1]: feature".to_string()]);
+
+        // A stored scan with a matching key is returned as is.
+        let mut cache: ScanCache = serde_json::from_slice(&std::fs::read(&cache_path).unwrap()).unwrap();
+        cache.repositories.values_mut().for_each(|scan| scan.info.commits = vec!["cached".to_string()]);
+        std::fs::write(&cache_path, serde_json::to_vec(&cache).unwrap()).unwrap();
+        assert_eq!(scan_cached(&repo, &GitConfig::default(), "feature", &cache_path).unwrap().commits, vec!["cached".to_string()]);
+
+        // New branches invalidate it.
+        let initial = repo.head().unwrap().peel_to_commit().unwrap().parent(0).unwrap();
+        repo.branch("other", &initial, false).unwrap();
+        assert_eq!(scan_cached(&repo, &GitConfig::default(), "feature", &cache_path).unwrap().commits, vec!["[TRACK-1]: feature".to_string()]);
+    }
+
+    /// Linear `main` of `depth` commits, a branch at every older commit and `feature` on top of `main`.
+    fn synthetic(depth: usize) -> (tempfile::TempDir, Repository) {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        {
+            let sig = git2::Signature::now("test", "test@example.com").unwrap();
+            let tree = repo.find_tree(repo.index().unwrap().write_tree().unwrap()).unwrap();
+
+            let mut parent = repo.find_commit(repo.commit(None, &sig, &sig, "0", &tree, &[]).unwrap()).unwrap();
+            for i in 1..depth {
+                repo.branch(format!("stale-{}", i).as_str(), &parent, false).unwrap();
+                parent = repo.find_commit(repo.commit(None, &sig, &sig, i.to_string().as_str(), &tree, &[&parent]).unwrap()).unwrap();
+            }
+            repo.branch("main", &parent, false).unwrap();
+
+            let feature = repo.commit(None, &sig, &sig, "feature", &tree, &[&parent]).unwrap();
+            repo.branch("feature", &repo.find_commit(feature).unwrap(), false).unwrap();
+            repo.set_head("refs/heads/feature").unwrap();
+        }
+        (dir, repo)
+    }
+
+    #[test]
+    fn test_base_scan_limit() {
+        let (_dir, repo) = synthetic(200);
+        let limited = GitConfig { base_scan_limit: Some(10), ..Default::default() };
+
+        let expected = scan_branches(&repo, &GitConfig::default(), "feature").unwrap();
+        assert_eq!(expected.bases, vec!["main".to_string()]);
+        assert_eq!(expected.commits, vec!["feature".to_string()]);
+        assert_eq!(scan_branches(&repo, &limited, "feature").unwrap(), expected);
+
+        // Every stale branch walks the whole history without the limit.
+        assert_eq!(index_branches(&repo, "feature", None).unwrap().len(), 200);
+        let indexed: usize = index_branches(&repo, "feature", Some(10)).unwrap().values().map(HashSet::len).sum();
+        let unlimited: usize = index_branches(&repo, "feature", None).unwrap().values().map(HashSet::len).sum();
+        assert!(indexed <= 200 * 10);
+        assert!(unlimited > 200 * 99);
+    }
+
+    #[test]
+    fn test_checked_out_elsewhere() {
+        let (dir, repo) = fixture();
+        checkout_feature(&repo);
+        let head = repo.head().unwrap().peel_to_commit().unwrap();
+        let other = repo.branch("other", &head, false).unwrap();
+
+        let path = dir.path().join("worktree");
+        let mut opts = git2::WorktreeAddOptions::new();
+        opts.reference(Some(other.get()));
+        let worktree = Repository::open_from_worktree(&repo.worktree("other", &path, Some(&opts)).unwrap()).unwrap();
+
+        assert_eq!(worktree.head().unwrap().shorthand(), Some("other"));
+        assert_eq!(checked_out_elsewhere(&worktree, "other").unwrap(), None);
+        assert_eq!(checked_out_elsewhere(&worktree, "feature").unwrap(), repo.workdir().map(Path::to_path_buf));
+        assert_eq!(checked_out_elsewhere(&repo, "feature").unwrap(), None);
+        assert_eq!(checked_out_elsewhere(&repo, "other").unwrap(), worktree.workdir().map(Path::to_path_buf));
+    }
+
+    #[test]
+    fn test_skip_merge_commits() {
+        let (_dir, repo) = synthetic(3);
+        {
+            let sig = git2::Signature::now("test", "test@example.com").unwrap();
+            let feature = repo.head().unwrap().peel_to_commit().unwrap();
+            let tree = feature.tree().unwrap();
+            let side = repo.find_commit(repo.commit(None, &sig, &sig, "side", &tree, &[&feature]).unwrap()).unwrap();
+            repo.commit(Some("HEAD"), &sig, &sig, "Merge branch 'side' into feature", &tree, &[&feature, &side]).unwrap();
+        }
+
+        let info = scan_branches(&repo, &GitConfig::default(), "feature").unwrap();
+        assert_eq!(info.bases, vec!["main".to_string()]);
+        let mut commits = info.commits.clone();
+        commits.sort();
+        assert_eq!(commits, vec!["feature".to_string(), "side".to_string()]);
+
+        let config = GitConfig { skip_merge_commits: false, ..Default::default() };
+        let info = scan_branches(&repo, &config, "feature").unwrap();
+        assert_eq!(info.bases, vec!["main".to_string()]);
+        assert_eq!(info.commits.len(), 3);
+        assert!(info.commits.contains(&"Merge branch 'side' into feature".to_string()));
+    }
+
+    #[test]
+    fn test_commit_subjects() {
+        let (_dir, repo) = synthetic(3);
+        let message = "[TRACK-1]: Subject line\n\nFirst paragraph\nwrapped.\n\nSecond paragraph.\n";
+        {
+            let sig = git2::Signature::now("test", "test@example.com").unwrap();
+            let feature = repo.head().unwrap().peel_to_commit().unwrap();
+            repo.commit(Some("HEAD"), &sig, &sig, message, &feature.tree().unwrap(), &[&feature]).unwrap();
+        }
+
+        let info = scan_branches(&repo, &GitConfig::default(), "feature").unwrap();
+
+        assert_eq!(info.commits, vec!["[TRACK-1]: Subject line".to_string(), "feature".to_string()]);
+        assert_eq!(info.messages, vec![message.trim().to_string(), "feature".to_string()]);
+    }
+}
@@ -1,12 +1,20 @@
+use std::path::{Path, PathBuf};
 use std::process::Command;
+use std::time::Instant;
 
 use serde::{Deserialize, Serialize};
+use tracing::{debug, debug_span};
+
+use crate::config::{Config, GitHubConfig};
+use crate::error::{Error, Result};
+use crate::forge::{Forge, NewPr, PrUpdate};
 
 const REVIEWERS_QUERY: &str = "query ($repo: String!, $owner: String!) {
   repository(name: $repo, owner: $owner) {
     assignableUsers(first: 100) {
       nodes {
         login
+        name
       }
       pageInfo {
         hasNextPage
@@ -16,14 +24,65 @@ const REVIEWERS_QUERY: &str = "query ($repo: String!, $owner: String!) {
   }
 }";
 
-#[derive(Serialize, Deserialize)]
-struct Login {
-    login: String,
+const RELATED_PR_QUERY: &str = "query ($login: String!) {
+  user(login: $login) {
+    pullRequests(last: 20) {
+      edges {
+        node {
+          id
+          title
+          resourcePath
+          number
+          body
+          state
+          isDraft
+          baseRefName
+          headRefName
+          updatedAt
+        }
+      }
+    }
+  }
+}";
+
+/// `--json` fields of `gh pr view` and `gh pr list` deserialized into [`PullRequestView`].
+const PR_VIEW_FIELDS: &str = "id,title,url,number,body,state,isDraft,baseRefName,headRefName,updatedAt";
+
+/// Assignable user of a repository, requested for review by `login`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Reviewer {
+    pub login: String,
+    /// Profile name, missing when the user didn't set one.
+    #[serde(default)]
+    pub name: Option<String>,
+}
+
+impl Reviewer {
+    /// Reviewer known only by `login`, eg. a team suggested by `CODEOWNERS`.
+    pub fn new(login: impl Into<String>) -> Self {
+        Self { login: login.into(), name: None }
+    }
+}
+
+impl std::fmt::Display for Reviewer {
+    /// `Name (login)`, or the login alone without a name.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.name.as_deref().filter(|name| !name.is_empty()) {
+            Some(name) => write!(f, "{} ({})", name, self.login),
+            None => write!(f, "{}", self.login),
+        }
+    }
+}
+
+impl AsRef<str> for Reviewer {
+    fn as_ref(&self) -> &str {
+        &self.login
+    }
 }
 
 #[derive(Serialize, Deserialize)]
 struct Nodes {
-    nodes: Vec<Login>,
+    nodes: Vec<Reviewer>,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -37,7 +96,7 @@ struct Repository {
     repository: AssignableUsers,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
 pub(crate) struct PullRequest {
     pub id: String,
     pub title: String,
@@ -45,6 +104,62 @@ pub(crate) struct PullRequest {
     pub resource_path: String,
     pub number: u32,
     pub body: String,
+    #[serde(default)]
+    pub state: PullRequestState,
+    #[serde(alias = "isDraft", default)]
+    pub is_draft: bool,
+    #[serde(alias = "baseRefName", default)]
+    pub base_ref_name: String,
+    #[serde(alias = "headRefName", default)]
+    pub head_ref_name: String,
+    /// RFC 3339 timestamp of the last update.
+    #[serde(alias = "updatedAt", default)]
+    pub updated_at: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "UPPERCASE")]
+pub(crate) enum PullRequestState {
+    #[default]
+    Open,
+    Closed,
+    Merged,
+}
+
+#[derive(Serialize, Deserialize)]
+struct PullRequestView {
+    id: String,
+    title: String,
+    url: String,
+    number: u32,
+    body: String,
+    #[serde(default)]
+    state: PullRequestState,
+    #[serde(alias = "isDraft", default)]
+    is_draft: bool,
+    #[serde(alias = "baseRefName", default)]
+    base_ref_name: String,
+    #[serde(alias = "headRefName", default)]
+    head_ref_name: String,
+    #[serde(alias = "updatedAt", default)]
+    updated_at: String,
+}
+
+impl From<PullRequestView> for PullRequest {
+    fn from(v: PullRequestView) -> Self {
+        PullRequest {
+            id: v.id,
+            title: v.title,
+            resource_path: resource_path_from_url(&v.url),
+            number: v.number,
+            body: v.body,
+            state: v.state,
+            is_draft: v.is_draft,
+            base_ref_name: v.base_ref_name,
+            head_ref_name: v.head_ref_name,
+            updated_at: v.updated_at,
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize)]
@@ -73,121 +188,485 @@ struct Response<D> {
     data: D,
 }
 
+fn gh(args: &[String]) -> Result<Vec<u8>> {
+    let _span = debug_span!("gh", command = %args.first().map(String::as_str).unwrap_or_default()).entered();
+    debug!(?args, "running gh");
 
-#[derive(Serialize, Deserialize)]
-struct CurrentBranch {
-    title: String,
+    let started = Instant::now();
+    let cmd = Command::new("gh")
+        .args(args)
+        .output()?;
+    debug!(status = %cmd.status, elapsed_ms = started.elapsed().as_millis() as u64, "gh finished");
+
+    if !cmd.status.success() {
+        let stderr = String::from_utf8_lossy(&cmd.stderr);
+        return Err(Error::GitHubCli(stderr.trim().to_string()));
+    }
+
+    Ok(cmd.stdout)
 }
 
-pub(crate) fn get_available_reviewers() -> Result<Vec<String>, String> {
-    let cmd = Command::new("gh")
-        .args(vec![
-            "api", "graphql",
-            "-F", "owner=:owner",
-            "-F", "repo=:repo",
-            "-f", format!("query={}", REVIEWERS_QUERY).as_str(),
-        ])
-        .output()
-        .expect("Failed to get available reviewers");
-
-    let v: Response<Repository> = serde_json::from_slice(cmd.stdout.as_slice())
-        .expect("expected to be json");
+/// [`Forge`] shelling out to `gh`.
+pub(crate) struct GhClient {
+    /// Don't run mutating commands, return them instead.
+    pub dry_run: bool,
+    /// In dry run, the PR body is written to this file and bodies of related PRs next to it,
+    /// the returned commands read them with `--body-file` instead of inlining them.
+    pub out: Option<PathBuf>,
+}
 
-    let nodes = v.data.repository.assignable_users.nodes;
-    Ok(nodes.into_iter().map(|node| -> String {
-        node.login
-    }).collect())
+impl GhClient {
+    /// Command of `args` for dry run, with the `-b` body moved to `path` when `out` is set.
+    fn dry_run_command(&self, mut args: Vec<String>, path: impl FnOnce(&Path) -> PathBuf) -> Result<String> {
+        if let (Some(out), Some(pos)) = (&self.out, args.iter().position(|a| a == "-b")) {
+            let path = path(out);
+            std::fs::write(&path, &args[pos + 1])?;
+            args[pos] = "-F".into();
+            args[pos + 1] = path.display().to_string();
+        }
+
+        Ok(shell_command("gh", &args))
+    }
 }
 
-const RELATED_PR_QUERY: &str = "query ($login: String!) {
-  user(login: $login) {
-    pullRequests(last: 20) {
-      edges {
-        node {
-          id
-          title
-          resourcePath
-          number
-          body
+/// `program` invocation of `args` quoted for a POSIX shell, so dry run output can be copied and run.
+/// `gh` and `glab` themselves are always run without a shell, arguments are passed literally.
+pub(crate) fn shell_command(program: &str, args: &[String]) -> String {
+    let mut command = String::from(program);
+    for arg in args {
+        command.push(' ');
+        command.push_str(&shell_quote(arg));
+    }
+    command
+}
+
+/// `arg` as is when it's made of characters a shell doesn't interpret, single-quoted otherwise.
+fn shell_quote(arg: &str) -> String {
+    let safe = |c: char| c.is_ascii_alphanumeric() || "@%+=:,./_-".contains(c);
+    if !arg.is_empty() && arg.chars().all(safe) {
+        return arg.to_string();
+    }
+
+    format!("'{}'", arg.replace('\'', r"'\''"))
+}
+
+impl Forge for GhClient {
+    fn publish_pr(&self, config: &Config, pr: &NewPr) -> Result<String> {
+        let args = create_args(&config.github, pr);
+
+        if self.dry_run {
+            return self.dry_run_command(args, Path::to_path_buf);
         }
-      }
+
+        let stdout = gh(&args)?;
+
+        Ok(String::from_utf8_lossy(&stdout).trim().to_string())
     }
-  }
-}";
 
-pub(crate) fn get_user_prs() -> Result<Vec<PullRequest>, String> {
-    let login = env!("GITHUB_USER", "Env GITHUB_USER not found!");
+    fn update_pr(&self, update: &PrUpdate) -> Result<String> {
+        let mut args: Vec<String> = vec![
+            "pr".into(), "edit".into(),
+            update.number.to_string(),
+            "--repo".into(), repo_from_resource_path(update.resource_path),
+        ];
+        if let Some(title) = update.title {
+            args.extend(["-t".into(), title.into()]);
+        }
+        if let Some(body) = update.body {
+            args.extend(["-b".into(), body.into()]);
+        }
+
+        if self.dry_run {
+            return self.dry_run_command(args, |out| related_out_path(out, update.number));
+        }
 
-    let cmd = Command::new("gh")
-        .args(vec![
-            "api", "graphql",
-            "-F", format!("login={}", login).as_str(),
-            "-f", format!("query={}", RELATED_PR_QUERY).as_str(),
-        ])
-        .output()
-        .expect("Failed to get available reviewers");
+        let stdout = gh(&args)?;
 
-    let v: Response<User> = serde_json::from_slice(cmd.stdout.as_slice())
-        .expect("expected to be json");
+        Ok(String::from_utf8_lossy(&stdout).trim().to_string())
+    }
 
-    let edges = v.data.user.pull_requests.edges;
-    Ok(edges.into_iter().map(|edge| -> PullRequest {
-        edge.node
-    }).collect())
-}
+    fn get_user_prs(&self, login: &str) -> Result<Vec<PullRequest>> {
+        let stdout = gh(&[
+            "api".into(), "graphql".into(),
+            "-F".into(), format!("login={}", login),
+            "-f".into(), format!("query={}", RELATED_PR_QUERY),
+        ])?;
 
-pub(crate) fn publish_pr(base: String, title: String, pr_body: String, reviewers: Vec<String>, dry_run: bool) -> Result<String, String> {
-    if dry_run {
-        println!("gh pr create -B {} -t {} -a @me -b {} -r {}", base, title, pr_body, reviewers.join(","));
+        let v: Response<User> = serde_json::from_slice(stdout.as_slice())?;
 
-        return Ok("Dry run".into());
+        let edges = v.data.user.pull_requests.edges;
+        Ok(edges.into_iter().map(|edge| -> PullRequest {
+            edge.node
+        }).collect())
     }
 
+    fn get_available_reviewers(&self, repo: Option<&str>, include_bots: bool) -> Result<Vec<Reviewer>> {
+        let stdout = gh(&reviewers_args(repo))?;
 
-    let cmd = Command::new("gh")
-        .args(vec![
-            "pr", "create",
-            "-B", format!("{}", base).as_str(),
-            "-t", format!("{}", title).as_str(),
-            "-a", "@me",
-            "-b", format!("{}", pr_body).as_str(),
-            "-r", reviewers.join(",").as_str(),
-        ])
-        .output()
-        .expect("Failed to create PR");
-
-    Ok(String::from_utf8(cmd.stdout).unwrap_or("Failed to get stdout".into()))
-}
-
-pub(crate) fn update_pr(pr: &u32, resource_path: &String, body: String, dry_run: bool) -> Result<String, String> {
-    let mut parts: Vec<&str> = resource_path.split("/").collect();
+        parse_reviewers(&stdout, include_bots)
+    }
+
+    fn get_authenticated_user(&self) -> Result<String> {
+        let stdout = gh(&[
+            "api".into(), "user".into(),
+            "--jq".into(), ".login".into(),
+        ])?;
+
+        Ok(String::from_utf8_lossy(&stdout).trim().to_string())
+    }
+
+    fn get_pr_by_branch(&self, repo: Option<&str>, branch: &str) -> Result<Option<PullRequest>> {
+        let mut args: Vec<String> = vec!["pr".into(), "list".into()];
+        if let Some(repo) = repo {
+            args.push("--repo".into());
+            args.push(repo.into());
+        }
+        args.extend([
+            "--head".into(), branch.into(),
+            "--state".into(), "open".into(),
+            "--limit".into(), "1".into(),
+            "--json".into(), PR_VIEW_FIELDS.into(),
+        ]);
+
+        first_pr(&gh(&args)?)
+    }
+
+    fn get_pr_by_number(&self, repo: Option<&str>, number: u32) -> Result<PullRequest> {
+        let mut args: Vec<String> = vec!["pr".into(), "view".into(), number.to_string()];
+        if let Some(repo) = repo {
+            args.push("--repo".into());
+            args.push(repo.into());
+        }
+        args.push("--json".into());
+        args.push(PR_VIEW_FIELDS.into());
+
+        let v: PullRequestView = serde_json::from_slice(gh(&args)?.as_slice())?;
+
+        Ok(v.into())
+    }
+
+    fn open_in_browser(&self, url: &str) -> Result<()> {
+        gh(&[
+            "pr".into(), "view".into(),
+            url.into(),
+            "--web".into(),
+        ])?;
+
+        Ok(())
+    }
+}
+
+fn first_pr(stdout: &[u8]) -> Result<Option<PullRequest>> {
+    let prs: Vec<PullRequestView> = serde_json::from_slice(stdout)?;
+
+    Ok(prs.into_iter().next().map(PullRequest::from))
+}
+
+/// `https://github.com/owner/repo/pull/1` -> `/owner/repo/pull/1`
+pub(crate) fn resource_path_from_url(url: &str) -> String {
+    let path = url.splitn(4, '/').nth(3).unwrap_or_default();
+    format!("/{}", path)
+}
+
+/// `repo` is `owner/name`, eg. of `--repo` or `github.remote`. Without it `gh` resolves
+/// `:owner`/`:repo` from the current directory, which is the fork in fork based workflows.
+fn reviewers_args(repo: Option<&str>) -> Vec<String> {
+    let (owner, name) = repo.and_then(|r| r.split_once('/')).unwrap_or((":owner", ":repo"));
+
+    vec![
+        "api".into(), "graphql".into(),
+        "-F".into(), format!("owner={}", owner),
+        "-F".into(), format!("repo={}", name),
+        "-f".into(), format!("query={}", REVIEWERS_QUERY),
+    ]
+}
+
+fn parse_reviewers(stdout: &[u8], include_bots: bool) -> Result<Vec<Reviewer>> {
+    let v: Response<Repository> = serde_json::from_slice(stdout)?;
+
+    let nodes = v.data.repository.assignable_users.nodes;
+    Ok(nodes.into_iter()
+        .filter(|reviewer| include_bots || !reviewer.login.ends_with("[bot]"))
+        .collect())
+}
+
+/// `pr.md` -> `pr.related-12.md`
+fn related_out_path(out: &Path, number: u32) -> PathBuf {
+    let stem = out.file_stem().unwrap_or_default().to_string_lossy();
+    let name = match out.extension() {
+        Some(ext) => format!("{}.related-{}.{}", stem, number, ext.to_string_lossy()),
+        None => format!("{}.related-{}", stem, number),
+    };
+    out.with_file_name(name)
+}
+
+/// `/owner/repo/pull/1` -> `owner/repo`, GitLab's `/group/project/-/merge_requests/1` -> `group/project`
+pub(crate) fn repo_from_resource_path(resource_path: &str) -> String {
+    let mut parts: Vec<&str> = resource_path.split('/').collect();
     parts.pop();            // removes pr number
-    parts.pop();            // removes "pull"
+    parts.pop();            // removes "pull" or "merge_requests"
+    if parts.last() == Some(&"-") {
+        parts.pop();
+    }
     parts.remove(0); // removes ""
 
-    let repo_url = parts.join("/");
+    parts.join("/")
+}
+
+fn create_args(config: &GitHubConfig, pr: &NewPr) -> Vec<String> {
+    let mut args: Vec<String> = vec!["pr".into(), "create".into()];
+    if let Some(repo) = pr.repo {
+        args.push("--repo".into());
+        args.push(repo.into());
+    }
+    args.extend([
+        "-B".into(), pr.base.into(),
+        "-t".into(), pr.title.into(),
+    ]);
+    let assignees = config.assignees();
+    if !assignees.is_empty() {
+        args.push("-a".into());
+        args.push(assignees.join(","));
+    }
+    args.push("-b".into());
+    args.push(pr.body.into());
+    if let Some(milestone) = &config.default_milestone {
+        args.push("--milestone".into());
+        args.push(milestone.clone());
+    }
+    // `gh` rejects --draft and --reviewer with --web, they're picked on the page.
+    if pr.web {
+        args.push("--web".into());
+        return args;
+    }
+    if config.draft {
+        args.push("--draft".into());
+    }
+    if !pr.reviewers.is_empty() {
+        args.push("-r".into());
+        args.push(pr.reviewers.join(","));
+    }
 
-    let pr_number = format!("{}", pr.clone());
-    let pr_body = format!("{}", body.clone());
-    let pr_url = format!("{}", repo_url.clone());
+    args
+}
 
-    if dry_run {
-        println!("gh pr edit {} --repo {} -b {}", pr_number, pr_url, pr_body);
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-        return Ok("Dry run".into());
+    #[test]
+    fn test_resource_path_from_url() {
+        assert_eq!(resource_path_from_url("https://github.com/owner/repo/pull/12"), "/owner/repo/pull/12");
     }
 
-    let cmd = Command::new("gh")
-        .args(vec![
-            "pr", "edit",
-            pr_number.as_str(),
-            "--repo", pr_url.as_str(),
-            "-b", pr_body.as_str(),
-        ])
-        .output()
-        .expect("Failed to create PR");
-
-
-    let stdout = String::from_utf8(cmd.stdout).unwrap_or("Failed to get stdout".into());
-    Ok(String::from(stdout.trim()))
+    #[test]
+    fn test_dry_run_out() {
+        let dir = tempfile::tempdir().unwrap();
+        let out = dir.path().join("pr.md");
+        let client = GhClient { dry_run: true, out: Some(out.clone()) };
+
+        let command = client.publish_pr(&Config::default(), &NewPr { body: "## Body\n", ..new_pr(&[]) }).unwrap();
+        assert!(command.contains(&format!("-F {}", out.display())));
+        assert!(!command.contains("## Body"));
+        assert_eq!(std::fs::read_to_string(&out).unwrap(), "## Body\n");
+
+        let command = client.update_pr(&PrUpdate { body: Some("related"), ..pr_update() }).unwrap();
+        let related = dir.path().join("pr.related-12.md");
+        assert_eq!(command, format!("gh pr edit 12 --repo owner/repo -F {}", related.display()));
+        assert_eq!(std::fs::read_to_string(related).unwrap(), "related");
+    }
+
+    #[test]
+    fn test_reviewers_args() {
+        let args = reviewers_args(Some("upstream-org/repo"));
+        assert_eq!(args[..6], ["api", "graphql", "-F", "owner=upstream-org", "-F", "repo=repo"]);
+
+        let args = reviewers_args(None);
+        assert_eq!(args[..6], ["api", "graphql", "-F", "owner=:owner", "-F", "repo=:repo"]);
+    }
+
+    #[test]
+    fn test_parse_reviewers() {
+        let stdout = br#"{"data":{"repository":{"assignableUsers":{"nodes":[{"login":"alice","name":"Alice Smith"},{"login":"dependabot[bot]","name":null},{"login":"bob","name":""},{"login":"renovate[bot]"}]}}}}"#;
+
+        let logins = |reviewers: Vec<Reviewer>| -> Vec<String> { reviewers.into_iter().map(|r| r.login).collect() };
+        assert_eq!(logins(parse_reviewers(stdout, false).unwrap()), vec!["alice", "bob"]);
+        assert_eq!(logins(parse_reviewers(stdout, true).unwrap()), vec!["alice", "dependabot[bot]", "bob", "renovate[bot]"]);
+
+        let reviewers = parse_reviewers(stdout, false).unwrap();
+        assert_eq!(reviewers[0].to_string(), "Alice Smith (alice)");
+        assert_eq!(reviewers[1].to_string(), "bob");
+    }
+
+    #[test]
+    fn test_first_pr() {
+        assert!(first_pr(b"[]").unwrap().is_none());
+
+        let stdout = br#"[{"id":"PR_1","title":"[AB-1]: title","url":"https://github.com/owner/repo/pull/7","number":7,"body":"body","state":"MERGED","isDraft":true}]"#;
+        let pr = first_pr(stdout).unwrap().unwrap();
+        assert_eq!(pr.number, 7);
+        assert_eq!(pr.resource_path, "/owner/repo/pull/7");
+        assert_eq!(pr.state, PullRequestState::Merged);
+        assert!(pr.is_draft);
+    }
+
+    #[test]
+    fn test_repo_from_resource_path() {
+        assert_eq!(repo_from_resource_path("/owner/repo/pull/12"), "owner/repo");
+        assert_eq!(repo_from_resource_path("/group/sub/project/-/merge_requests/12"), "group/sub/project");
+    }
+
+    #[test]
+    fn test_update_pr_title_dry_run() {
+        let client = GhClient { dry_run: true, out: None };
+
+        let output = client.update_pr(&PrUpdate { title: Some("[NEW-1]: title"), ..pr_update() }).unwrap();
+        assert_eq!(output, "gh pr edit 12 --repo owner/repo -t '[NEW-1]: title'");
+    }
+
+    /// Body with characters a shell would interpret.
+    const SHELL_SENSITIVE_BODY: &str = "## This PR...\n\nRuns `make` and $(rm -rf ~) with ${HOME}\n\"quoted\" and 'single' \\ \\n; echo done | tee *\n";
+
+    #[test]
+    fn test_create_args_pass_body_literally() {
+        let args = create_args(&GitHubConfig::default(), &NewPr { title: "[AB-1]: `$(title)`", body: SHELL_SENSITIVE_BODY, ..new_pr(&[]) });
+
+        let pos = args.iter().position(|a| a == "-b").unwrap();
+        assert_eq!(args[pos + 1], SHELL_SENSITIVE_BODY);
+        let pos = args.iter().position(|a| a == "-t").unwrap();
+        assert_eq!(args[pos + 1], "[AB-1]: `$(title)`");
+    }
+
+    #[test]
+    fn test_shell_quote() {
+        assert_eq!(shell_quote("owner/repo"), "owner/repo");
+        assert_eq!(shell_quote(""), "''");
+        assert_eq!(shell_quote("it's"), r"'it'\''s'");
+        assert_eq!(shell_command("gh", &["pr".into(), "edit".into(), "-t".into(), "a b".into()]), "gh pr edit -t 'a b'");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_shell_quote_round_trips_through_sh() {
+        let script = format!("printf %s {}", shell_quote(SHELL_SENSITIVE_BODY));
+        let output = Command::new("sh").arg("-c").arg(script).output().unwrap();
+
+        assert_eq!(String::from_utf8(output.stdout).unwrap(), SHELL_SENSITIVE_BODY);
+    }
+
+    /// Arguments of the `gh` command line, as a shell splits them.
+    #[cfg(unix)]
+    fn shell_args(command: &str) -> Vec<String> {
+        let args = command.strip_prefix("gh ").unwrap();
+        let output = Command::new("sh").arg("-c").arg(format!("printf '%s\\0' {}", args)).output().unwrap();
+
+        let stdout = String::from_utf8(output.stdout).unwrap();
+        stdout.split_terminator('\0').map(String::from).collect()
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_dry_run_commands_round_trip() {
+        let client = GhClient { dry_run: true, out: None };
+        let config = Config::default();
+        let title = "[AB-1]: \"quoted\" `title`";
+
+        let reviewers = ["bob".to_string()];
+        let pr = NewPr { repo: Some("owner/repo"), title, body: SHELL_SENSITIVE_BODY, ..new_pr(&reviewers) };
+
+        let command = client.publish_pr(&config, &pr).unwrap();
+        let expected = create_args(&config.github, &pr);
+        assert_eq!(shell_args(&command), expected);
+
+        let command = client.update_pr(&PrUpdate { body: Some(SHELL_SENSITIVE_BODY), ..pr_update() }).unwrap();
+        assert_eq!(shell_args(&command), vec!["pr", "edit", "12", "--repo", "owner/repo", "-b", SHELL_SENSITIVE_BODY]);
+    }
+
+    fn new_pr(reviewers: &[String]) -> NewPr<'_> {
+        NewPr { base: "main", title: "title", body: "body", reviewers, ..Default::default() }
+    }
+
+    fn pr_update() -> PrUpdate<'static> {
+        PrUpdate { number: 12, resource_path: "/owner/repo/pull/12", ..Default::default() }
+    }
+
+    #[test]
+    fn test_create_args_self_assign() {
+        let config = GitHubConfig::default();
+        let args = create_args(&config, &new_pr(&["octocat".to_string()]));
+
+        let pos = args.iter().position(|a| a == "-a").unwrap();
+        assert_eq!(args[pos + 1], "@me");
+    }
+
+    #[test]
+    fn test_create_args_without_self_assign() {
+        let config = GitHubConfig {
+            self_assign: false,
+            ..Default::default()
+        };
+        let args = create_args(&config, &new_pr(&["octocat".to_string()]));
+
+        assert!(!args.contains(&"-a".to_string()));
+        assert!(!args.contains(&"@me".to_string()));
+    }
+
+    #[test]
+    fn test_create_args_explicit_assignees() {
+        let config = GitHubConfig {
+            self_assign: false,
+            assignees: vec!["human".to_string()],
+            ..Default::default()
+        };
+        let args = create_args(&config, &new_pr(&["octocat".to_string()]));
+
+        let pos = args.iter().position(|a| a == "-a").unwrap();
+        assert_eq!(args[pos + 1], "human");
+    }
+
+    #[test]
+    fn test_create_args_repo() {
+        let config = GitHubConfig::default();
+        let args = create_args(&config, &NewPr { repo: Some("IAmRadek/git-pr"), ..new_pr(&["octocat".to_string()]) });
+
+        let pos = args.iter().position(|a| a == "--repo").unwrap();
+        assert_eq!(args[pos + 1], "IAmRadek/git-pr");
+    }
+
+    #[test]
+    fn test_create_args_draft() {
+        let config = GitHubConfig::default();
+        assert!(!create_args(&config, &new_pr(&[])).contains(&"--draft".to_string()));
+
+        let config = GitHubConfig { draft: true, ..Default::default() };
+        assert!(create_args(&config, &new_pr(&[])).contains(&"--draft".to_string()));
+    }
+
+    #[test]
+    fn test_create_args_milestone() {
+        let config = GitHubConfig { default_milestone: Some("v1.2 release".to_string()), ..Default::default() };
+        let args = create_args(&config, &new_pr(&[]));
+
+        let pos = args.iter().position(|a| a == "--milestone").unwrap();
+        assert_eq!(args[pos + 1], "v1.2 release");
+        assert!(!create_args(&GitHubConfig::default(), &new_pr(&[])).contains(&"--milestone".to_string()));
+    }
+
+    #[test]
+    fn test_create_args_web() {
+        let config = GitHubConfig { draft: true, ..Default::default() };
+        let args = create_args(&config, &NewPr { web: true, ..new_pr(&["octocat".to_string()]) });
+
+        assert_eq!(args.last().unwrap(), "--web");
+        assert!(!args.contains(&"--draft".to_string()));
+        assert!(!args.contains(&"-r".to_string()));
+    }
+
+    #[test]
+    fn test_create_args_without_reviewers() {
+        let config = GitHubConfig::default();
+        let args = create_args(&config, &new_pr(&[]));
+
+        assert!(!args.contains(&"-r".to_string()));
+    }
 }
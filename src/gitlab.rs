@@ -0,0 +1,306 @@
+use std::process::Command;
+use std::time::Instant;
+
+use serde::Deserialize;
+use tracing::{debug, debug_span};
+
+use crate::config::{Config, GitHubConfig};
+use crate::error::{Error, Result};
+use crate::forge::{Forge, NewPr, PrUpdate};
+use crate::github::{self, PullRequest, PullRequestState, Reviewer};
+
+/// Merge request of the GitLab REST API.
+#[derive(Deserialize)]
+struct MergeRequest {
+    id: u64,
+    iid: u32,
+    title: String,
+    #[serde(default)]
+    description: Option<String>,
+    state: String,
+    #[serde(default)]
+    draft: bool,
+    #[serde(default)]
+    target_branch: String,
+    #[serde(default)]
+    source_branch: String,
+    #[serde(default)]
+    updated_at: String,
+    web_url: String,
+}
+
+impl From<MergeRequest> for PullRequest {
+    fn from(mr: MergeRequest) -> Self {
+        PullRequest {
+            id: mr.id.to_string(),
+            title: mr.title,
+            resource_path: github::resource_path_from_url(&mr.web_url),
+            number: mr.iid,
+            body: mr.description.unwrap_or_default(),
+            state: match mr.state.as_str() {
+                "merged" => PullRequestState::Merged,
+                "closed" | "locked" => PullRequestState::Closed,
+                _ => PullRequestState::Open,
+            },
+            is_draft: mr.draft,
+            base_ref_name: mr.target_branch,
+            head_ref_name: mr.source_branch,
+            updated_at: mr.updated_at,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct Member {
+    username: String,
+    #[serde(default)]
+    name: Option<String>,
+    #[serde(default)]
+    bot: bool,
+}
+
+#[derive(Deserialize)]
+struct User {
+    username: String,
+}
+
+fn glab(args: &[String]) -> Result<Vec<u8>> {
+    let _span = debug_span!("glab", command = %args.first().map(String::as_str).unwrap_or_default()).entered();
+    debug!(?args, "running glab");
+
+    let started = Instant::now();
+    let cmd = Command::new("glab")
+        .args(args)
+        .output()?;
+    debug!(status = %cmd.status, elapsed_ms = started.elapsed().as_millis() as u64, "glab finished");
+
+    if !cmd.status.success() {
+        let stderr = String::from_utf8_lossy(&cmd.stderr);
+        return Err(Error::GitLabCli(stderr.trim().to_string()));
+    }
+
+    Ok(cmd.stdout)
+}
+
+/// `glab api` request of `endpoint`, relative to `/api/v4`.
+fn api<T: serde::de::DeserializeOwned>(endpoint: String) -> Result<T> {
+    let stdout = glab(&["api".into(), endpoint])?;
+
+    Ok(serde_json::from_slice(&stdout)?)
+}
+
+/// [`Forge`] shelling out to `glab`, the `github` config section applies to merge requests too.
+pub(crate) struct GlabClient {
+    /// Don't run mutating commands, return them instead.
+    pub dry_run: bool,
+}
+
+impl GlabClient {
+    fn run(&self, args: Vec<String>) -> Result<String> {
+        if self.dry_run {
+            return Ok(github::shell_command("glab", &args));
+        }
+
+        let stdout = glab(&args)?;
+
+        Ok(String::from_utf8_lossy(&stdout).trim().to_string())
+    }
+}
+
+impl Forge for GlabClient {
+    fn publish_pr(&self, config: &Config, pr: &NewPr) -> Result<String> {
+        // `glab` doesn't know `@me`, the read-only user lookup runs in dry run too
+        // so the printed command can be run as is.
+        let me = if config.github.assignees().iter().any(|login| login == "@me") {
+            self.get_authenticated_user()?
+        } else {
+            String::new()
+        };
+        let output = self.run(create_args(&config.github, pr, &me))?;
+        if self.dry_run {
+            return Ok(output);
+        }
+
+        // The url ends the progress messages of `glab mr create`.
+        Ok(output.split_whitespace().rfind(|word| word.starts_with("http")).unwrap_or(&output).to_string())
+    }
+
+    fn update_pr(&self, update: &PrUpdate) -> Result<String> {
+        let mut args: Vec<String> = vec![
+            "mr".into(), "update".into(),
+            update.number.to_string(),
+            "-R".into(), github::repo_from_resource_path(update.resource_path),
+        ];
+        if let Some(title) = update.title {
+            args.extend(["--title".into(), title.into()]);
+        }
+        if let Some(body) = update.body {
+            args.extend(["--description".into(), body.into()]);
+        }
+
+        self.run(args)
+    }
+
+    fn get_user_prs(&self, login: &str) -> Result<Vec<PullRequest>> {
+        let endpoint = format!("merge_requests?author_username={}&scope=all&order_by=updated_at&per_page=20", encode(login));
+        let mrs: Vec<MergeRequest> = api(endpoint)?;
+
+        Ok(mrs.into_iter().map(PullRequest::from).collect())
+    }
+
+    fn get_available_reviewers(&self, repo: Option<&str>, include_bots: bool) -> Result<Vec<Reviewer>> {
+        let members: Vec<Member> = api(format!("projects/{}/members/all?per_page=100", project(repo)))?;
+
+        Ok(members.into_iter()
+            .filter(|member| include_bots || !member.bot)
+            .map(|member| Reviewer { login: member.username, name: member.name })
+            .collect())
+    }
+
+    fn get_authenticated_user(&self) -> Result<String> {
+        let user: User = api("user".into())?;
+
+        Ok(user.username)
+    }
+
+    fn get_pr_by_branch(&self, repo: Option<&str>, branch: &str) -> Result<Option<PullRequest>> {
+        let endpoint = format!("projects/{}/merge_requests?source_branch={}&state=opened&per_page=1", project(repo), encode(branch));
+        let mrs: Vec<MergeRequest> = api(endpoint)?;
+
+        Ok(mrs.into_iter().next().map(PullRequest::from))
+    }
+
+    fn get_pr_by_number(&self, repo: Option<&str>, number: u32) -> Result<PullRequest> {
+        let mr: MergeRequest = api(format!("projects/{}/merge_requests/{}", project(repo), number))?;
+
+        Ok(mr.into())
+    }
+
+    fn open_in_browser(&self, url: &str) -> Result<()> {
+        let resource_path = github::resource_path_from_url(url);
+        let number = resource_path.rsplit('/').next().unwrap_or_default().to_string();
+        glab(&[
+            "mr".into(), "view".into(),
+            number,
+            "-R".into(), github::repo_from_resource_path(&resource_path),
+            "--web".into(),
+        ])?;
+
+        Ok(())
+    }
+}
+
+fn create_args(config: &GitHubConfig, pr: &NewPr, me: &str) -> Vec<String> {
+    let mut args: Vec<String> = vec!["mr".into(), "create".into()];
+    if let Some(repo) = pr.repo {
+        args.push("-R".into());
+        args.push(repo.into());
+    }
+    args.extend([
+        "--target-branch".into(), pr.base.into(),
+        "--title".into(), pr.title.into(),
+        "--description".into(), pr.body.into(),
+        "--yes".into(),
+    ]);
+    let assignees: Vec<String> = config.assignees().into_iter()
+        .map(|login| if login == "@me" { me.to_string() } else { login })
+        .collect();
+    if !assignees.is_empty() {
+        args.push("--assignee".into());
+        args.push(assignees.join(","));
+    }
+    if let Some(milestone) = &config.default_milestone {
+        args.push("--milestone".into());
+        args.push(milestone.clone());
+    }
+    // Same as with `gh`, draft and reviewers are picked on the page.
+    if pr.web {
+        args.push("--web".into());
+        return args;
+    }
+    if config.draft {
+        args.push("--draft".into());
+    }
+    if !pr.reviewers.is_empty() {
+        args.push("--reviewer".into());
+        args.push(pr.reviewers.join(","));
+    }
+
+    args
+}
+
+/// Project of the API endpoints, `repo` is `group/project`, the one of the current directory when missing.
+fn project(repo: Option<&str>) -> String {
+    repo.map(encode).unwrap_or_else(|| ":id".to_string())
+}
+
+/// Percent-encodes `value` for a path segment or query value.
+fn encode(value: &str) -> String {
+    let mut encoded = String::new();
+    for byte in value.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => encoded.push(byte as char),
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn new_pr(reviewers: &[String]) -> NewPr<'_> {
+        NewPr { base: "main", title: "title", body: "body", reviewers, ..Default::default() }
+    }
+
+    #[test]
+    fn test_create_args() {
+        let config = GitHubConfig { assignees: vec!["human".to_string()], draft: true, ..Default::default() };
+        let args = create_args(&config, &NewPr { repo: Some("group/project"), ..new_pr(&["octocat".to_string()]) }, "me");
+
+        assert_eq!(args, vec![
+            "mr", "create", "-R", "group/project",
+            "--target-branch", "main", "--title", "title", "--description", "body", "--yes",
+            "--assignee", "me,human", "--draft", "--reviewer", "octocat",
+        ]);
+
+        let args = create_args(&config, &NewPr { web: true, ..new_pr(&["octocat".to_string()]) }, "me");
+        assert_eq!(args.last().unwrap(), "--web");
+        assert!(!args.contains(&"--draft".to_string()));
+    }
+
+    #[test]
+    fn test_update_pr_dry_run() {
+        let client = GlabClient { dry_run: true };
+
+        let update = PrUpdate { number: 12, resource_path: "/group/project/-/merge_requests/12", body: Some("Related PRs"), ..Default::default() };
+        let command = client.update_pr(&update).unwrap();
+        assert_eq!(command, "glab mr update 12 -R group/project --description 'Related PRs'");
+    }
+
+    #[test]
+    fn test_merge_request() {
+        let json = r#"[{
+            "id": 101, "iid": 12, "title": "[AB-1]: title", "description": null, "state": "merged",
+            "draft": true, "target_branch": "main", "source_branch": "feature",
+            "updated_at": "2024-03-01T10:00:00Z", "web_url": "https://gitlab.com/group/project/-/merge_requests/12"
+        }]"#;
+        let mrs: Vec<MergeRequest> = serde_json::from_str(json).unwrap();
+        let pr = PullRequest::from(mrs.into_iter().next().unwrap());
+
+        assert_eq!(pr.number, 12);
+        assert_eq!(pr.resource_path, "/group/project/-/merge_requests/12");
+        assert_eq!(pr.state, PullRequestState::Merged);
+        assert_eq!(pr.body, "");
+        assert_eq!(pr.base_ref_name, "main");
+        assert!(pr.is_draft);
+    }
+
+    #[test]
+    fn test_project() {
+        assert_eq!(project(Some("group/sub project")), "group%2Fsub%20project");
+        assert_eq!(project(None), ":id");
+        assert_eq!(encode("feature/a&b"), "feature%2Fa%26b");
+    }
+}
@@ -0,0 +1,27 @@
+//! Highly opinionated tool for PR creation.
+//!
+//! The `git-pr` binary is a thin wrapper around [`app::run`], embedders can
+//! build a [`PullRequest`] themselves and publish it with [`create_pr`].
+
+pub mod app;
+pub mod cli;
+pub mod config;
+pub mod error;
+pub mod pr;
+
+mod codeowners;
+mod forge;
+mod git;
+mod github;
+mod gitlab;
+mod jira;
+mod tags;
+mod template;
+mod ui;
+
+pub use app::{create_pr, CreatedPr, CreateOptions, RelatedUpdate, UpdateOutcome};
+pub use config::Config;
+pub use error::{Error, Result};
+pub use github::Reviewer;
+pub use pr::PullRequest;
+pub use ui::{InquirePrompter, Prompter};
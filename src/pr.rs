@@ -0,0 +1,170 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::Result;
+use crate::template;
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PullRequest {
+    pub title: String,
+    pub tag: String,
+    pub is_jira: bool,
+    /// Values of `template.fields` by field name.
+    pub fields: HashMap<String, String>,
+    pub reviewers: Vec<String>,
+    pub base: String,
+    pub branch: String,
+    /// Full messages of the branch commits, newest first.
+    pub commits: Vec<String>,
+    /// Content added to the rendered body, see `template.append_position`.
+    pub appendix: Option<String>,
+    /// Issues closed by the PR once merged, the `{{closes}}` placeholder.
+    pub closes: Vec<u32>,
+}
+
+impl PullRequest {
+    /// Built-in placeholders available in field defaults.
+    pub fn placeholders(&self) -> HashMap<String, String> {
+        HashMap::from([
+            ("tag".to_string(), self.tag.clone()),
+            ("branch".to_string(), self.branch.clone()),
+            ("base".to_string(), self.base.clone()),
+            ("commits".to_string(), commit_list(&self.commits)),
+            ("closes".to_string(), template::closing_keywords(&self.closes)),
+        ])
+    }
+
+    /// Message of the only commit of the branch without its subject line, `None` for
+    /// branches with more commits or a subject-only message.
+    pub fn single_commit_body(&self) -> Option<String> {
+        let [message] = self.commits.as_slice() else {
+            return None;
+        };
+        let body = message.split_once('\n').map(|(_, body)| body.trim()).unwrap_or_default();
+
+        (!body.is_empty()).then(|| body.to_string())
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    pub fn from_json(json: &str) -> Result<Self> {
+        Ok(serde_json::from_str(json)?)
+    }
+
+    /// Draft file of `branch` in `dir`, `%` and `/` are percent-encoded so distinct branches never share a file.
+    pub fn draft_path(dir: &Path, branch: &str) -> PathBuf {
+        dir.join(format!("{}.json", branch.replace('%', "%25").replace('/', "%2F")))
+    }
+
+    /// Saves the PR as the draft of its branch, returns the path of the draft.
+    pub fn save_draft(&self, dir: &Path) -> Result<PathBuf> {
+        let path = Self::draft_path(dir, &self.branch);
+        std::fs::write(&path, self.to_json()?)?;
+
+        Ok(path)
+    }
+
+    /// Draft saved for `branch`, if any.
+    pub fn load_draft(dir: &Path, branch: &str) -> Result<Option<Self>> {
+        let path = Self::draft_path(dir, branch);
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        Ok(Some(Self::from_json(&std::fs::read_to_string(path)?)?))
+    }
+
+    pub fn delete_draft(dir: &Path, branch: &str) -> Result<()> {
+        let path = Self::draft_path(dir, branch);
+        if path.exists() {
+            std::fs::remove_file(path)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Markdown list of commit messages, oldest first, bodies indented under their subject.
+fn commit_list(messages: &[String]) -> String {
+    messages.iter().rev()
+        .map(|message| {
+            let mut lines = message.lines();
+            let subject = format!("- {}", lines.next().unwrap_or_default());
+            let body = lines.map(|line| if line.is_empty() { String::new() } else { format!("  {}", line) });
+
+            std::iter::once(subject).chain(body).collect::<Vec<String>>().join("\n")
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_commits_placeholder() {
+        let pr = PullRequest {
+            commits: vec!["Second\n\nWith body\nwrapped".to_string(), "First".to_string()],
+            ..Default::default()
+        };
+
+        assert_eq!(pr.placeholders()["commits"], "- First\n- Second\n\n  With body\n  wrapped");
+    }
+
+    #[test]
+    fn test_single_commit_body() {
+        let mut pr = PullRequest {
+            commits: vec!["Subject\n\nRich body\nwrapped\n".to_string()],
+            ..Default::default()
+        };
+        assert_eq!(pr.single_commit_body().as_deref(), Some("Rich body\nwrapped"));
+
+        pr.commits = vec!["Subject".to_string()];
+        assert_eq!(pr.single_commit_body(), None);
+
+        pr.commits = vec!["Second\n\nBody".to_string(), "First\n\nBody".to_string()];
+        assert_eq!(pr.single_commit_body(), None);
+    }
+
+    #[test]
+    fn test_drafts() {
+        let dir = tempfile::tempdir().unwrap();
+        let pr = PullRequest {
+            title: "[TRACK-1]: Draft".to_string(),
+            branch: "feature/draft".to_string(),
+            fields: HashMap::from([("description".to_string(), "Half written".to_string())]),
+            reviewers: vec!["alice".to_string()],
+            ..Default::default()
+        };
+
+        assert_eq!(PullRequest::from_json(&pr.to_json().unwrap()).unwrap(), pr);
+        assert_eq!(PullRequest::load_draft(dir.path(), "feature/draft").unwrap(), None);
+
+        let path = pr.save_draft(dir.path()).unwrap();
+        assert_eq!(path, dir.path().join("feature%2Fdraft.json"));
+        assert_eq!(PullRequest::load_draft(dir.path(), "feature/draft").unwrap(), Some(pr));
+        assert_eq!(PullRequest::load_draft(dir.path(), "feature_draft").unwrap(), None);
+        assert_eq!(PullRequest::load_draft(dir.path(), "feature%2Fdraft").unwrap(), None);
+
+        PullRequest::delete_draft(dir.path(), "feature/draft").unwrap();
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn test_draft_path() {
+        let dir = Path::new("drafts");
+        let branches = ["feature/a", "feature_a", "feature%2Fa", "feature%a", "feature%252Fa"];
+        let paths: Vec<_> = branches.iter().map(|branch| PullRequest::draft_path(dir, branch)).collect();
+
+        for (i, path) in paths.iter().enumerate() {
+            assert!(!paths[i + 1..].contains(path), "{} collides", branches[i]);
+        }
+        assert_eq!(paths[0], dir.join("feature%2Fa.json"));
+    }
+}
@@ -1,11 +1,15 @@
+use std::collections::HashMap;
 use std::io::{Read, Write};
 use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
 
 use inquire::{Autocomplete, CustomUserError};
 use inquire::autocompletion::Replacement;
 use lazy_static::lazy_static;
 use regex::Regex;
 
+use crate::config::TagRanking;
+
 lazy_static! {
     static ref PATTERN: Regex = Regex::new(r"\[(\w+\-?)*]").unwrap();
 }
@@ -27,51 +31,65 @@ pub(crate) fn extract_from_str(message: &str) -> Option<String> {
     None
 }
 
+/// `message` without its leading `[tag]` and the `:` following it, eg. `Fix it` of `[TRACK-1]: Fix it`.
+/// Repeated leading tags, eg. of a title tagged twice, are all stripped, other brackets are kept.
+pub(crate) fn strip_from_str(message: &str, tag: &str) -> String {
+    let prefix = format!("[{}]", tag);
+    let mut title = message.trim_start();
+    while let Some(rest) = title.strip_prefix(&prefix) {
+        title = rest.strip_prefix(':').unwrap_or(rest).trim_start();
+    }
+    title.to_string()
+}
 
+
+/// Recently used tags, persisted one `TAG\tcount\tlast_used` line per tag, most recent first.
 #[derive(Debug, Default, Clone)]
 pub struct Tags {
     file: String,
     tags: Vec<String>,
+    stats: HashMap<String, TagStats>,
+    ranking: TagRanking,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct TagStats {
+    /// Number of times the tag was used.
+    count: usize,
+    /// Unix time of the last use, unknown for tags saved by older versions.
+    last_used: Option<u64>,
 }
 
+impl Default for TagStats {
+    fn default() -> Self {
+        Self { count: 1, last_used: None }
+    }
+}
+
+/// Days for the weight of a use to halve in [`TagRanking::Hybrid`].
+const HALF_LIFE_DAYS: f64 = 14.0;
+
 impl Autocomplete for Tags {
     fn get_suggestions(&mut self, input: &str) -> Result<Vec<String>, CustomUserError> {
-        let mut suggestions = Vec::new();
-        for tag in self.tags.iter() {
-            if tag.starts_with(input) {
-                suggestions.push(tag.clone());
-            }
-        }
-        Ok(suggestions)
+        Ok(self.ranked().into_iter()
+            .filter(|tag| tag.starts_with(input))
+            .collect())
     }
 
     fn get_completion(&mut self, input: &str, _highlighted_suggestion: Option<String>) -> Result<Replacement, CustomUserError> {
-        for tag in self.tags.iter() {
-            if tag.starts_with(input) {
-                return Ok(Some(tag.clone()));
-            }
-        }
-        Ok(None)
+        Ok(self.ranked().into_iter().find(|tag| tag.starts_with(input)))
     }
 }
 
 
 impl Tags {
-    pub fn validator(ticket: &str) -> Result<inquire::validator::Validation, inquire::CustomUserError> {
-        if PATTERN.is_match(ticket) {
-            Ok(inquire::validator::Validation::Valid)
-        } else {
-            Ok(inquire::validator::Validation::Invalid("This does not looks like valid TAG ticket (eg. TRACK-123)".into()))
-        }
-    }
-
     pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, std::io::Error> {
         let path = path.as_ref();
 
         if !path.exists() {
             return Ok(Self {
                 file: path.to_str().unwrap().to_string(),
-                tags: Vec::new(),
+                ..Default::default()
             });
         }
 
@@ -80,43 +98,132 @@ impl Tags {
         let mut contents = String::new();
         file.read_to_string(&mut contents)?;
 
-        let mut tags = Vec::new();
+        let mut tags = Self::parse(&contents);
+        tags.file = path.to_str().unwrap().to_string();
+
+        Ok(tags)
+    }
+
+    /// Lines without a count or a last use, written by older versions, count as a single use.
+    fn parse(contents: &str) -> Self {
+        let mut tags = Self::default();
         for line in contents.lines() {
-            let tag = line.trim();
-            tags.push(tag.to_string());
+            let mut columns = line.split('\t');
+            let tag = columns.next().unwrap_or_default().trim();
+            if tag.is_empty() || tags.contains(tag) {
+                continue;
+            }
+            let mut stats = TagStats::default();
+            if let Some(count) = columns.next().and_then(|count| count.trim().parse().ok()) {
+                stats.count = count;
+            }
+            stats.last_used = columns.next().and_then(|last_used| last_used.trim().parse().ok());
+
+            tags.tags.push(tag.to_string());
+            tags.stats.insert(tag.to_string(), stats);
         }
+        tags
+    }
 
-        Ok(Self {
-            file: path.to_str().unwrap().to_string(),
+    pub fn from_vec(tags: Vec<String>) -> Self {
+        Self {
+            file: String::new(),
+            stats: tags.iter().map(|tag| (tag.clone(), TagStats::default())).collect(),
             tags,
-        })
+            ranking: TagRanking::default(),
+        }
+    }
+
+    /// Order of suggestions and of the tags kept when the history is full.
+    pub fn with_ranking(mut self, ranking: TagRanking) -> Self {
+        self.ranking = ranking;
+        self
     }
 
+    /// Tags, most recent first.
     pub fn iter(&self) -> Vec<String> {
         self.tags.clone()
     }
 
-    pub fn add(&mut self, tag: String) {
-        if self.tags.contains(&tag) {
-            self.tags.retain(|t| t != &tag);
+    /// Tags by `ranking`, best first.
+    pub fn ranked(&self) -> Vec<String> {
+        self.ranked_at(unix_now())
+    }
+
+    /// Tags by `ranking` at `now` (unix time), best first. Ties keep the most recent first.
+    fn ranked_at(&self, now: u64) -> Vec<String> {
+        let mut ranked = self.tags.clone();
+        match self.ranking {
+            TagRanking::Recency => {}
+            TagRanking::Frequency => ranked.sort_by_key(|tag| std::cmp::Reverse(self.count(tag))),
+            TagRanking::Hybrid => ranked.sort_by(|a, b| self.score(b, now).total_cmp(&self.score(a, now))),
         }
-        self.tags.insert(0, tag);
+        ranked
+    }
+
+    /// Uses of `tag` weighted by the age of the last one, halving every [`HALF_LIFE_DAYS`].
+    fn score(&self, tag: &str, now: u64) -> f64 {
+        let Some(stats) = self.stats.get(tag) else {
+            return 0.0;
+        };
+        let age_days = stats.last_used.map_or(0.0, |last_used| now.saturating_sub(last_used) as f64 / 86_400.0);
+
+        stats.count as f64 * 0.5_f64.powf(age_days / HALF_LIFE_DAYS)
+    }
+
+    pub fn contains(&self, tag: &str) -> bool {
+        self.tags.iter().any(|t| t == tag)
+    }
+
+    pub fn len(&self) -> usize {
+        self.tags.len()
+    }
+
+    /// Number of times `tag` was used, 0 for unknown tags.
+    pub fn count(&self, tag: &str) -> usize {
+        self.stats.get(tag).map(|stats| stats.count).unwrap_or_default()
+    }
+
+    pub fn add(&mut self, tag: String) {
+        self.add_at(tag, unix_now());
+    }
+
+    /// Adds a use of `tag` at `now` (unix time). A full history drops its worst ranked other tag.
+    fn add_at(&mut self, tag: String, now: u64) {
+        let stats = match self.stats.get(&tag) {
+            Some(stats) => TagStats { count: stats.count + 1, last_used: Some(now) },
+            None => TagStats { count: 1, last_used: Some(now) },
+        };
+        self.tags.retain(|t| t != &tag);
+        self.tags.insert(0, tag.clone());
+        self.stats.insert(tag.clone(), stats);
 
         if self.tags.len() > 10 {
-            self.tags.pop();
+            if let Some(dropped) = self.ranked_at(now).into_iter().rev().find(|t| t != &tag) {
+                self.tags.retain(|t| t != &dropped);
+                self.stats.remove(&dropped);
+            }
         }
     }
 
-    pub fn save(self) -> std::io::Result<()> {
-        let mut file = std::fs::File::create(self.file)?;
-        for tag in self.tags {
-            file.write_all(tag.as_bytes())?;
-            file.write_all(b"\n")?;
-        }
-        Ok(())
+    pub fn save(&self) -> std::io::Result<()> {
+        let mut file = std::fs::File::create(&self.file)?;
+        file.write_all(self.to_file_contents().as_bytes())
+    }
+
+    fn to_file_contents(&self) -> String {
+        self.tags.iter()
+            .map(|tag| {
+                let stats = self.stats.get(tag).copied().unwrap_or_default();
+                match stats.last_used {
+                    Some(last_used) => format!("{}\t{}\t{}\n", tag, stats.count, last_used),
+                    None => format!("{}\t{}\n", tag, stats.count),
+                }
+            })
+            .collect()
     }
 
-    pub fn add_and_save(mut self, tag: String) -> std::io::Result<()> {
+    pub fn add_and_save(&mut self, tag: String) -> std::io::Result<()> {
         self.add(tag);
         self.save()
     }
@@ -126,11 +233,28 @@ impl Tags {
     }
 }
 
+fn unix_now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or_default()
+}
+
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_strip_from_str() {
+        assert_eq!(strip_from_str("[TRACK-1]: Fix it", "TRACK-1"), "Fix it");
+        assert_eq!(strip_from_str("[TRACK-1] Fix it", "TRACK-1"), "Fix it");
+        assert_eq!(strip_from_str("Fix it [TRACK-1]", "TRACK-1"), "Fix it [TRACK-1]");
+        assert_eq!(strip_from_str("Fix it", "TRACK-1"), "Fix it");
+        assert_eq!(strip_from_str("[TRACK-1]: [TRACK-1]: Fix it", "TRACK-1"), "Fix it");
+        assert_eq!(strip_from_str("[TRACK-1]: Fix [TRACK-2]", "TRACK-1"), "Fix [TRACK-2]");
+        assert_eq!(strip_from_str("[TRACK-2]: Fix it", "TRACK-1"), "[TRACK-2]: Fix it");
+        assert_eq!(strip_from_str("Use array[0] indexing", "TRACK-1"), "Use array[0] indexing");
+        assert_eq!(strip_from_str("Fix [ci] flake", "TRACK-1"), "Fix [ci] flake");
+    }
+
     #[test]
     fn test_tags() {
         let mut tags = Tags::from_file("pr_tags.txt").unwrap();
@@ -145,4 +269,71 @@ mod tests {
         assert_eq!(tags.tags[0], "TRACK-124");
         assert_eq!(tags.tags[1], "TRACK-123");
     }
+
+    #[test]
+    fn test_parse_with_and_without_counts() {
+        let tags = Tags::parse("TRACK-2\t3\nTRACK-1\n  OPS-7 \nOPS-8\tbroken\n\nTRACK-2\t9\n");
+
+        assert_eq!(tags.iter(), vec!["TRACK-2", "TRACK-1", "OPS-7", "OPS-8"]);
+        assert_eq!(tags.len(), 4);
+        assert_eq!(tags.count("TRACK-2"), 3);
+        assert_eq!(tags.count("TRACK-1"), 1);
+        assert_eq!(tags.count("OPS-7"), 1);
+        assert_eq!(tags.count("OPS-8"), 1);
+        assert_eq!(tags.count("NOPE-1"), 0);
+        assert!(tags.contains("OPS-7"));
+        assert!(!tags.contains("OPS"));
+
+        assert_eq!(tags.to_file_contents(), "TRACK-2\t3\nTRACK-1\t1\nOPS-7\t1\nOPS-8\t1\n");
+    }
+
+    #[test]
+    fn test_suggestions_ranked_by_use() {
+        let mut tags = Tags::from_vec(vec!["TRACK-1".to_string(), "TRACK-2".to_string()]);
+        tags.add("TRACK-2".to_string());
+        tags.add("TRACK-2".to_string());
+        tags.add("TRACK-3".to_string());
+
+        assert_eq!(tags.iter(), vec!["TRACK-3", "TRACK-2", "TRACK-1"]);
+        assert_eq!(tags.count("TRACK-2"), 3);
+        assert_eq!(tags.get_suggestions("TRACK").unwrap(), vec!["TRACK-2", "TRACK-3", "TRACK-1"]);
+        assert_eq!(tags.get_completion("TR", None).unwrap(), Some("TRACK-2".to_string()));
+    }
+
+    #[test]
+    fn test_parse_last_used() {
+        let tags = Tags::parse("TRACK-2\t3\t1700000000\nTRACK-1\t2\tbroken\n");
+
+        assert_eq!(tags.stats["TRACK-2"], TagStats { count: 3, last_used: Some(1_700_000_000) });
+        assert_eq!(tags.stats["TRACK-1"], TagStats { count: 2, last_used: None });
+        assert_eq!(tags.to_file_contents(), "TRACK-2\t3\t1700000000\nTRACK-1\t2\n");
+    }
+
+    #[test]
+    fn test_ranking() {
+        const DAY: u64 = 86_400;
+        let now = 100 * DAY;
+        // Most recent first: a single use today, 4 uses 6 weeks ago, 2 uses last week.
+        let tags = Tags::parse(&format!("NEW-1\t1\t{}\nOLD-1\t4\t{}\nWEEK-1\t2\t{}\n", now, now - 42 * DAY, now - 7 * DAY));
+
+        assert_eq!(tags.clone().with_ranking(TagRanking::Recency).ranked_at(now), vec!["NEW-1", "OLD-1", "WEEK-1"]);
+        assert_eq!(tags.clone().with_ranking(TagRanking::Frequency).ranked_at(now), vec!["OLD-1", "WEEK-1", "NEW-1"]);
+        assert_eq!(tags.with_ranking(TagRanking::Hybrid).ranked_at(now), vec!["WEEK-1", "NEW-1", "OLD-1"]);
+    }
+
+    #[test]
+    fn test_full_history_drops_worst_ranked() {
+        let mut tags = Tags::from_vec(Vec::new()).with_ranking(TagRanking::Frequency);
+        for i in 0..10 {
+            tags.add_at(format!("T-{}", i), i);
+            tags.add_at(format!("T-{}", i), i);
+        }
+        tags.add_at("T-0".to_string(), 20);
+        tags.add_at("NEW-1".to_string(), 21);
+
+        assert_eq!(tags.len(), 10);
+        assert!(tags.contains("NEW-1"));
+        assert!(tags.contains("T-0"));
+        assert!(!tags.contains("T-1"));
+    }
 }
\ No newline at end of file
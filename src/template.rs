@@ -1,52 +1,757 @@
-use regex::Regex;
+use lazy_static::lazy_static;
+use std::collections::{HashMap, HashSet};
 
-use crate::github::PullRequest;
+use regex::{Captures, Regex};
 
-pub(crate) const TEMPLATE: &str = "Tracked by <!-- ISSUE_URL -->
+use crate::config::{AppendPosition, Config, TrackingLinePosition};
+use crate::github::{PullRequest, PullRequestState};
+
+pub(crate) const TEMPLATE: &str = "<!-- ISSUE_URL -->
 Related PRs:
 <!-- RELATED_PR -->
-- [ABCD-XXXX](https://example.com/ABCD-XXXX)
-- [ABCD-XXXX](https://example.com/ABCD-XXXX)
 <!-- /RELATED_PR -->
 
 ## This PR...
 
-<!-- THIS PR -->
+{{description}}
 
 ## Considerations and implementation
 
-<!-- IMPLEMENTATION -->
+{{implementation}}
 ";
 
-pub(crate) fn make_body(jira_ticket: &String, is_jira_ticket: &bool, this_pr: &String, implementation: &String) -> String {
-    let jira_url = env!("JIRA_URL", "Unable to find JIRA_URL env");
+lazy_static! {
+    /// Entries generated by `replace_related_prs`, eg. `- owner/repo/pull/12 - (this pr)`
+    /// or `- group/project/-/merge_requests/12` on GitLab.
+    static ref RELATED_PR_ENTRY: Regex = Regex::new(r"^- \S+/\S+/(pull|-/merge_requests)/\d+").unwrap();
+    static ref PLACEHOLDER: Regex = Regex::new(r"\{\{(\w+)\}\}").unwrap();
+}
+
+/// Placeholders available without a matching field, see `PullRequest::placeholders`.
+pub(crate) const RESERVED_PLACEHOLDERS: &[&str] = &["tag", "branch", "base", "commits", "closes"];
+
+/// Expanded to the closing keywords of `--closes`, its line is dropped without them.
+const CLOSES: &str = "{{closes}}";
+
+/// Marks the line of the template body replaced with the Jira tracking line.
+const ISSUE_URL: &str = "<!-- ISSUE_URL -->";
+
+/// PR values and per-run tweaks of [`make_body`], besides the field values.
+#[derive(Debug, Default, Clone, Copy)]
+pub(crate) struct BodyOptions<'a> {
+    /// Tag of the PR, linked by the Jira tracking line.
+    pub tag: &'a str,
+    /// Whether `tag` is a tracked Jira ticket getting the tracking line.
+    pub is_jira: bool,
+    /// Added at `template.append_position`, eg. the content of `--append-body`.
+    pub appendix: Option<&'a str>,
+    /// Leaves out the Jira tracking line even for Jira tickets, eg. with `--no-jira-link`.
+    pub no_jira_link: bool,
+    /// Issues of the `{{closes}}` placeholder.
+    pub closes: &'a [u32],
+    /// Values of the other reserved placeholders, eg. of `PullRequest::placeholders`, fields take precedence.
+    pub placeholders: Option<&'a HashMap<String, String>>,
+}
+
+pub(crate) fn make_body(config: &Config, fields: &HashMap<String, String>, opts: &BodyOptions) -> String {
+    let tracking_line = tracking_line(config, opts.tag, opts.is_jira && !opts.no_jira_link);
+
+    let body = normalize_line_endings(&config.template.body);
+    let mut template = place_tracking_line(&body, tracking_line.as_deref(), config.jira.tracking_line_position);
+    template = expand_closes(&template, opts.closes);
+    template = template.replace("<!-- RELATED_PR -->", config.markers.start.as_str());
+    template = template.replace("<!-- /RELATED_PR -->", config.markers.end.as_str());
+
+    let mut values = opts.placeholders.cloned().unwrap_or_default();
+    values.extend(fields.iter().map(|(name, value)| (name.clone(), normalize_line_endings(value))));
+    template = expand_placeholders(&template, &neutralize_markers(config, &values));
+    if let Some(appendix) = opts.appendix.map(str::trim).filter(|a| !a.is_empty()) {
+        let appendix = neutralize_value(config, &normalize_line_endings(appendix));
+        template = append_to_body(config, &template, &appendix, config.template.append_position);
+    }
+
+    // Renders the section of a PR without related PRs yet.
+    replace_related_prs(config, &template, &0, &[])
+}
+
+/// GitHub keywords closing `issues` on merge, eg. `Closes #1, Closes #2`.
+pub(crate) fn closing_keywords(issues: &[u32]) -> String {
+    issues.iter()
+        .map(|issue| format!("Closes #{}", issue))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Replaces `{{closes}}` of the template `body`, dropping the lines holding only it without `issues`.
+fn expand_closes(body: &str, issues: &[u32]) -> String {
+    if !issues.is_empty() {
+        return body.replace(CLOSES, &closing_keywords(issues));
+    }
+
+    let mut lines: Vec<&str> = body.lines().filter(|line| line.trim() != CLOSES).collect();
+    if body.ends_with('\n') {
+        lines.push("");
+    }
+    lines.join("\n").replace(CLOSES, "")
+}
+
+/// Template files and editor output may come with `\r\n` line endings, bodies are built with `\n`.
+fn normalize_line_endings(value: &str) -> String {
+    value.replace("\r\n", "\n")
+}
+
+/// Breaks markers typed into field values, they would otherwise be taken for the ones
+/// of the template and corrupt the related PRs section.
+fn neutralize_markers(config: &Config, fields: &HashMap<String, String>) -> HashMap<String, String> {
+    fields.iter()
+        .map(|(name, value)| (name.clone(), neutralize_value(config, value)))
+        .collect()
+}
+
+fn neutralize_value(config: &Config, value: &str) -> String {
+    let markers = [ISSUE_URL, "<!-- RELATED_PR -->", "<!-- /RELATED_PR -->", &config.markers.start, &config.markers.end];
+
+    let mut value = value.to_string();
+    for marker in markers.iter().filter(|m| !m.is_empty()) {
+        value = value.replace(marker, &neutralize(marker));
+    }
+    value
+}
+
+/// Puts `appendix` as a paragraph at `position` of `body`, at its end without a related PRs section.
+fn append_to_body(config: &Config, body: &str, appendix: &str, position: AppendPosition) -> String {
+    let section = related_prs_regex(config).find(body);
+    let at = match (position, section) {
+        (AppendPosition::BeforeRelated, Some(section)) => {
+            return format!("{}{}\n\n{}", &body[..section.start()], appendix, &body[section.start()..]);
+        }
+        (AppendPosition::AfterRelated, Some(section)) => section.end(),
+        _ => body.len(),
+    };
+
+    let (head, tail) = body.split_at(at);
+    let head = head.trim_end();
+    let separator = if head.is_empty() { "" } else { "\n\n" };
+    let tail = match tail.trim_start_matches('\n') {
+        "" => "\n".to_string(),
+        rest => format!("\n\n{}", rest),
+    };
+    format!("{}{}{}{}", head, separator, appendix, tail)
+}
+
+/// `<` is HTML-escaped so the marker still renders as typed, other markers get a zero-width space.
+fn neutralize(marker: &str) -> String {
+    let mut chars = marker.chars();
+    match chars.next() {
+        Some('<') => format!("&lt;{}", chars.as_str()),
+        Some(first) => format!("{}\u{200B}{}", first, chars.as_str()),
+        None => String::new(),
+    }
+}
+
+fn tracking_line(config: &Config, jira_ticket: &str, is_jira_ticket: bool) -> Option<String> {
+    if !is_jira_ticket || config.jira.tracking_line_template.is_empty() {
+        return None;
+    }
+
+    let values = HashMap::from([
+        ("tag".to_string(), jira_ticket.to_string()),
+        ("url".to_string(), config.jira.url.clone().unwrap_or_default()),
+        ("link".to_string(), config.jira.link(jira_ticket)),
+    ]);
+    Some(expand_placeholders(&config.jira.tracking_line_template, &values))
+}
+
+/// Drops the `<!-- ISSUE_URL -->` line of `body` and puts `line` at `position` instead.
+fn place_tracking_line(body: &str, line: Option<&str>, position: TrackingLinePosition) -> String {
+    let marker = body.lines().position(|l| l.contains(ISSUE_URL));
+    let mut lines: Vec<&str> = body.lines().filter(|l| !l.contains(ISSUE_URL)).collect();
+
+    if let Some(line) = line {
+        match (position, marker) {
+            (TrackingLinePosition::Marker, Some(i)) => lines.insert(i, line),
+            (TrackingLinePosition::Append, _) => lines.extend(["", line]),
+            _ => {
+                lines.insert(0, "");
+                lines.insert(0, line);
+            }
+        }
+    }
+
+    let mut result = lines.join("\n");
+    if body.ends_with('\n') {
+        result.push('\n');
+    }
+    result
+}
+
+/// Replaces the `{{name}}` placeholders of `value` in a single pass, so placeholders inside the
+/// inserted values, eg. in a code block of a field, are kept as typed. Unknown ones are kept too.
+pub(crate) fn expand_placeholders(value: &str, values: &HashMap<String, String>) -> String {
+    PLACEHOLDER.replace_all(value, |captures: &Captures| {
+        values.get(&captures[1]).cloned().unwrap_or_else(|| captures[0].to_string())
+    }).to_string()
+}
+
+/// PR title of `title` tagged `tag`, following `template.title_format`.
+pub(crate) fn format_title(config: &Config, tag: &str, title: &str) -> String {
+    let values = HashMap::from([
+        ("tag".to_string(), tag.to_string()),
+        ("title".to_string(), title.to_string()),
+    ]);
+    expand_placeholders(&config.template.title_format, &values)
+}
+
+/// Names of the `{{name}}` placeholders of `value`, in order of appearance.
+pub(crate) fn placeholder_names(value: &str) -> Vec<String> {
+    PLACEHOLDER.captures_iter(value)
+        .map(|captures| captures[1].to_string())
+        .collect()
+}
+
+/// Entry of `pr` in the related PRs list, rendered with `template.related_pr_item`.
+fn related_pr_item(format: &str, pr: &PullRequest, is_this_pr: bool) -> String {
+    let values = HashMap::from([
+        ("number".to_string(), pr.number.to_string()),
+        ("title".to_string(), pr.title.clone()),
+        ("path".to_string(), pr.resource_path.replacen('/', "", 1)),
+        ("status".to_string(), status_marker(pr).to_string()),
+        ("this_pr".to_string(), if is_this_pr { " - (this pr)".to_string() } else { String::new() }),
+    ]);
+    expand_placeholders(format, &values)
+}
+
+/// Empty for open PRs, which most of the list is.
+fn status_marker(pr: &PullRequest) -> &'static str {
+    match pr.state {
+        PullRequestState::Merged => " (merged)",
+        PullRequestState::Closed => " (closed)",
+        PullRequestState::Open if pr.is_draft => " (draft)",
+        PullRequestState::Open => "",
+    }
+}
+
+/// Matches entries rendered by [`related_pr_item`] with `format`, so they are regenerated
+/// rather than kept as hand-written notes.
+fn related_pr_item_regex(format: &str) -> Regex {
+    let mut pattern = String::from("^");
+    let mut last = 0;
+    for captures in PLACEHOLDER.captures_iter(format) {
+        let placeholder = captures.get(0).unwrap();
+        pattern.push_str(&regex::escape(&format[last..placeholder.start()]));
+        pattern.push_str(match &captures[1] {
+            "number" => r"\d+",
+            "path" => r"\S+/(pull|-/merge_requests)/\d+",
+            _ => ".*",
+        });
+        last = placeholder.end();
+    }
+    pattern.push_str(&regex::escape(&format[last..]));
+
+    Regex::new(&pattern).unwrap()
+}
+
+fn related_prs_regex(config: &Config) -> Regex {
+    Regex::new(format!(
+        r"(?sm)^(?:<details><summary>[^\n]*</summary>\s*)?{}(.*){}(?:\s*</details>)?",
+        regex::escape(&config.markers.start),
+        regex::escape(&config.markers.end),
+    ).as_str()).unwrap()
+}
+
+/// `body` with an empty related PRs section appended when it has none.
+pub(crate) fn with_related_prs_section(config: &Config, body: &str) -> String {
+    if related_prs_regex(config).is_match(&normalize_line_endings(body)) {
+        return body.to_string();
+    }
+
+    let newline = if body.contains("\r\n") { "\r\n" } else { "\n" };
+    let mut body = body.trim_end().to_string();
+    if !body.is_empty() {
+        body.push_str(newline);
+        body.push_str(newline);
+    }
+    format!("{}Related PRs:{nl}{}{nl}{}{nl}", body, config.markers.start, config.markers.end, nl = newline)
+}
+
+/// Copies the related PRs section of `from` into `to`.
+pub(crate) fn carry_related_prs(config: &Config, from: &str, to: &str) -> String {
+    let from = normalize_line_endings(from);
+    let from = from.as_str();
+    let re = related_prs_regex(config);
+    match re.find(from) {
+        Some(section) => re.replace(to, regex::NoExpand(section.as_str())).to_string(),
+        None => to.to_string(),
+    }
+}
+
+/// Best effort extraction of field values from a body rendered from `template`.
+/// A field is found when the lines around its placeholder are present in the body.
+pub(crate) fn extract_fields(template: &str, body: &str, names: &[String]) -> HashMap<String, String> {
+    let (template, body) = (normalize_line_endings(template), normalize_line_endings(body));
+    let (template, body) = (template.as_str(), body.as_str());
+    let mut fields = HashMap::new();
+    for name in names {
+        let placeholder = format!("{{{{{}}}}}", name);
+        let Some(pos) = template.find(placeholder.as_str()) else {
+            continue;
+        };
 
-    let mut template = TEMPLATE.to_string();
-    if *is_jira_ticket {
-        template = template.replace("<!-- ISSUE_URL -->", format!("[{}]({}{})", jira_ticket.as_str(), jira_url, jira_ticket.as_str()).as_str());
-    } else {
-        template = template.replace("Tracked by <!-- ISSUE_URL -->", "");
+        let before = template[..pos].lines().rev().map(str::trim).find(|l| !l.is_empty());
+        let after = template[pos + placeholder.len()..].lines().map(str::trim).find(|l| !l.is_empty());
+
+        let start = match before {
+            Some(anchor) => match body.find(anchor) {
+                Some(i) => i + anchor.len(),
+                None => continue,
+            },
+            None => 0,
+        };
+        let end = match after {
+            Some(anchor) => match body[start..].find(anchor) {
+                Some(i) => start + i,
+                None => continue,
+            },
+            None => body.len(),
+        };
+
+        fields.insert(name.clone(), body[start..end].trim().to_string());
+    }
+    fields
+}
+
+/// `prs` from the bottom of the stack up, with their depth in it, when every PR is based on the head
+/// of the previous one. Otherwise sorted by number, all at depth 0.
+fn stack_order(prs: &[PullRequest]) -> Vec<(&PullRequest, usize)> {
+    let mut sorted: Vec<&PullRequest> = prs.iter().collect();
+    sorted.sort_by_key(|pr| pr.number);
+
+    let flat = sorted.iter().map(|pr| (*pr, 0)).collect();
+    let heads: HashSet<&str> = sorted.iter().map(|pr| pr.head_ref_name.as_str()).collect();
+    if heads.len() != sorted.len() || heads.contains("") {
+        return flat;
+    }
+
+    let mut roots = sorted.iter().filter(|pr| !heads.contains(pr.base_ref_name.as_str()));
+    let (Some(root), None) = (roots.next(), roots.next()) else {
+        return flat;
+    };
+
+    let mut chain = vec![*root];
+    while chain.len() < sorted.len() {
+        let head = chain[chain.len() - 1].head_ref_name.as_str();
+        let mut children = sorted.iter().filter(|pr| pr.base_ref_name == head);
+        match (children.next(), children.next()) {
+            (Some(child), None) => chain.push(child),
+            _ => return flat,
+        }
+    }
+
+    chain.into_iter().enumerate().map(|(depth, pr)| (pr, depth)).collect()
+}
+
+/// Line endings of `body` are kept, so a body edited on GitHub (`\r\n`) only changes with the list.
+pub(crate) fn replace_related_prs(config: &Config, body: &str, this_pr: &u32, related_prs: &[PullRequest]) -> String {
+    let crlf = body.contains("\r\n");
+    let body = normalize_line_endings(body);
+    let body = body.as_str();
+    let markers = &config.markers;
+
+    let item_format = &config.template.related_pr_item;
+    let mut related_prs_body: Vec<String> = vec![markers.start.clone()];
+    for (pr, depth) in stack_order(related_prs) {
+        let item = related_pr_item(item_format, pr, *this_pr == pr.number);
+        related_prs_body.push(format!("{}{}", "  ".repeat(depth), item));
+    }
+    let placeholder = markers.empty_placeholder.trim();
+    if related_prs.is_empty() && !placeholder.is_empty() {
+        related_prs_body.push(placeholder.to_string());
+    }
+
+    let re = related_prs_regex(config);
+    let item = related_pr_item_regex(item_format);
+
+    // Keep anything that was written by hand between the markers.
+    if let Some(captures) = re.captures(body) {
+        for line in captures[1].lines() {
+            let line = line.trim_end();
+            let entry = line.trim_start();
+            if !entry.is_empty() && entry != placeholder && !RELATED_PR_ENTRY.is_match(entry) && !item.is_match(entry) {
+                related_prs_body.push(line.to_string());
+            }
+        }
+    }
+    related_prs_body.push(markers.end.clone());
+
+    if markers.collapsible {
+        let summary = markers.summary_format.replace("{count}", related_prs.len().to_string().as_str());
+        related_prs_body.insert(0, format!("<details><summary>{}</summary>\n", summary));
+        related_prs_body.push("</details>".into());
     }
-    template = template.replace("<!-- THIS PR -->", this_pr.as_str());
-    template = template.replace("<!-- IMPLEMENTATION -->", implementation.as_str());
 
-    return template;
+    let result = re.replace_all(body, regex::NoExpand(related_prs_body.join("\n").as_str()));
+
+    if crlf {
+        return result.replace('\n', "\r\n");
+    }
+    result.to_string()
 }
 
-pub(crate) fn replace_related_prs(body: &String, this_pr: &u32, related_prs: &Vec<PullRequest>) -> String {
-    let mut related_prs_body: Vec<String> = vec!["<!-- RELATED_PR -->".into()];
-    for pr in related_prs {
-        let resource_path = pr.resource_path.replacen("/", "", 1);
-        if *this_pr == pr.number {
-            related_prs_body.push(format!("- {} - (this pr)", resource_path));
-        } else {
-            related_prs_body.push(format!("- {}", resource_path));
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pull_request(number: u32) -> PullRequest {
+        PullRequest {
+            id: number.to_string(),
+            title: format!("[TRACK-1]: PR {}", number),
+            resource_path: format!("/owner/repo/pull/{}", number),
+            number,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_expand_placeholders() {
+        let values = HashMap::from([
+            ("tag".to_string(), "TRACK-1".to_string()),
+            ("branch".to_string(), "feature".to_string()),
+        ]);
+
+        assert_eq!(expand_placeholders("Implements {{tag}} on {{branch}}", &values), "Implements TRACK-1 on feature");
+        assert_eq!(expand_placeholders("{{unknown}} {{tag}}", &values), "{{unknown}} TRACK-1");
+    }
+
+    #[test]
+    fn test_make_body_keeps_braces_of_field_values() {
+        let description = "Example:\n```rust\nlet v = vec![{{implementation}}];\n```\n{{ handlebars }} {{tag}}";
+        let fields = HashMap::from([
+            ("description".to_string(), description.to_string()),
+            ("implementation".to_string(), "Carefully".to_string()),
+        ]);
+
+        let body = make_body(&Config::default(), &fields, &BodyOptions::default());
+        assert!(body.contains(&format!("## This PR...\n\n{}\n", description)));
+        assert!(body.ends_with("## Considerations and implementation\n\nCarefully\n"));
+    }
+
+    fn jira_config(position: TrackingLinePosition) -> Config {
+        let mut config = Config::default();
+        config.jira.url = Some("https://example.atlassian.net/browse/".to_string());
+        config.jira.tracking_line_position = position;
+        config.template.body = "<!-- ISSUE_URL -->\n## This PR...\n\n{{description}}\n".to_string();
+        config
+    }
+
+    #[test]
+    fn test_make_body_tracking_line() {
+        let fields = HashMap::from([("description".to_string(), "Adds things.".to_string())]);
+
+        let body = make_body(&jira_config(TrackingLinePosition::Marker), &fields, &tracked());
+        assert_eq!(body, "Tracked by [TRACK-1](https://example.atlassian.net/browse/TRACK-1)\n## This PR...\n\nAdds things.\n");
+
+        let body = make_body(&jira_config(TrackingLinePosition::Marker), &fields, &BodyOptions { tag: "CHORE", ..Default::default() });
+        assert_eq!(body, "## This PR...\n\nAdds things.\n");
+
+        let opts = BodyOptions { no_jira_link: true, ..tracked() };
+        let body = make_body(&jira_config(TrackingLinePosition::Marker), &fields, &opts);
+        assert_eq!(body, "## This PR...\n\nAdds things.\n");
+    }
+
+    #[test]
+    fn test_make_body_closes() {
+        let fields = HashMap::from([("description".to_string(), "Adds things, see {{closes}}".to_string())]);
+        let mut config = Config::default();
+        config.template.body = "{{description}}\n\n{{closes}}\nAlso fixes: {{closes}}\n".to_string();
+        config.markers.empty_placeholder = String::new();
+
+        let opts = BodyOptions { closes: &[12, 34], ..Default::default() };
+        let body = make_body(&config, &fields, &opts);
+        assert_eq!(body, "Adds things, see {{closes}}\n\nCloses #12, Closes #34\nAlso fixes: Closes #12, Closes #34\n");
+
+        let body = make_body(&config, &fields, &BodyOptions::default());
+        assert_eq!(body, "Adds things, see {{closes}}\n\nAlso fixes: \n");
+    }
+
+    #[test]
+    fn test_make_body_placeholders() {
+        let mut config = Config::default();
+        config.template.body = "{{description}}\n\nBranch {{branch}} on {{base}}, {{unknown}}\n".to_string();
+        let fields = HashMap::from([("description".to_string(), "Adds {{branch}}".to_string())]);
+        let placeholders = HashMap::from([
+            ("branch".to_string(), "feature".to_string()),
+            ("base".to_string(), "main".to_string()),
+        ]);
+
+        let body = make_body(&config, &fields, &BodyOptions { placeholders: Some(&placeholders), ..Default::default() });
+        assert_eq!(body, "Adds {{branch}}\n\nBranch feature on main, {{unknown}}\n");
+    }
+
+    #[test]
+    fn test_make_body_neutralizes_markers() {
+        let mut config = Config::default();
+        config.markers.start = "[//]: # (related)".to_string();
+        config.template.body = TEMPLATE.replace("<!-- RELATED_PR -->", "[//]: # (related)");
+        let fields = HashMap::from([
+            ("description".to_string(), "Moves the <!-- /RELATED_PR --> marker\n<!-- ISSUE_URL -->".to_string()),
+            ("implementation".to_string(), "Uses [//]: # (related)".to_string()),
+        ]);
+
+        let body = make_body(&config, &fields, &BodyOptions::default());
+        assert!(body.contains("Moves the &lt;!-- /RELATED_PR --> marker\n&lt;!-- ISSUE_URL -->"));
+        assert!(body.contains("Uses [\u{200B}//]: # (related)"));
+
+        let result = replace_related_prs(&config, &body, &1, &[pull_request(1), pull_request(2)]);
+        assert!(result.contains("[//]: # (related)\n- owner/repo/pull/1 - (this pr)\n- owner/repo/pull/2\n<!-- /RELATED_PR -->\n\n## This PR..."));
+        assert!(result.ends_with("Uses [\u{200B}//]: # (related)\n"));
+    }
+
+    #[test]
+    fn test_with_related_prs_section() {
+        let config = Config::default();
+
+        assert_eq!(with_related_prs_section(&config, TEMPLATE), TEMPLATE);
+        assert_eq!(with_related_prs_section(&config, "Adds things.\n\n"), "Adds things.\n\nRelated PRs:\n<!-- RELATED_PR -->\n<!-- /RELATED_PR -->\n");
+        assert_eq!(with_related_prs_section(&config, "Adds\r\nthings."), "Adds\r\nthings.\r\n\r\nRelated PRs:\r\n<!-- RELATED_PR -->\r\n<!-- /RELATED_PR -->\r\n");
+        assert_eq!(with_related_prs_section(&config, ""), "Related PRs:\n<!-- RELATED_PR -->\n<!-- /RELATED_PR -->\n");
+    }
+
+    fn appended(appendix: Option<&str>) -> BodyOptions<'_> {
+        BodyOptions { appendix, ..Default::default() }
+    }
+
+    fn tracked() -> BodyOptions<'static> {
+        BodyOptions { tag: "TRACK-1", is_jira: true, ..Default::default() }
+    }
+
+    #[test]
+    fn test_make_body_appendix() {
+        let fields = HashMap::from([("description".to_string(), "Adds things.".to_string())]);
+        let mut config = Config::default();
+        config.template.body = "Related PRs:\n<!-- RELATED_PR -->\n<!-- /RELATED_PR -->\n\n{{description}}\n".to_string();
+        config.markers.empty_placeholder = String::new();
+        let appendix = Some("## Changelog\r\n- fix\n<!-- RELATED_PR -->\n\n");
+
+        let body = make_body(&config, &fields, &appended(appendix));
+        assert_eq!(body, "Related PRs:\n<!-- RELATED_PR -->\n<!-- /RELATED_PR -->\n\nAdds things.\n\n## Changelog\n- fix\n&lt;!-- RELATED_PR -->\n");
+
+        config.template.append_position = AppendPosition::BeforeRelated;
+        let body = make_body(&config, &fields, &appended(Some("Checklist")));
+        assert_eq!(body, "Related PRs:\nChecklist\n\n<!-- RELATED_PR -->\n<!-- /RELATED_PR -->\n\nAdds things.\n");
+
+        config.template.append_position = AppendPosition::AfterRelated;
+        let body = make_body(&config, &fields, &appended(Some("Checklist")));
+        assert_eq!(body, "Related PRs:\n<!-- RELATED_PR -->\n<!-- /RELATED_PR -->\n\nChecklist\n\nAdds things.\n");
+
+        let result = replace_related_prs(&config, &body, &1, &[pull_request(1)]);
+        assert!(result.contains("<!-- /RELATED_PR -->\n\nChecklist\n\nAdds things.\n"));
+
+        config.template.body = "{{description}}".to_string();
+        assert_eq!(make_body(&config, &fields, &appended(Some("Checklist"))), "Adds things.\n\nChecklist\n");
+        assert_eq!(make_body(&config, &fields, &appended(Some("  \n"))), "Adds things.");
+    }
+
+    #[test]
+    fn test_make_body_without_related_prs() {
+        let fields = HashMap::from([("description".to_string(), "Adds things.".to_string())]);
+        let mut config = Config::default();
+
+        let body = make_body(&config, &fields, &BodyOptions::default());
+        assert!(body.starts_with("Related PRs:\n<!-- RELATED_PR -->\nNo related PRs yet\n<!-- /RELATED_PR -->\n\n## This PR..."));
+
+        let result = replace_related_prs(&config, &body, &1, &[pull_request(1), pull_request(2)]);
+        assert!(result.starts_with("Related PRs:\n<!-- RELATED_PR -->\n- owner/repo/pull/1 - (this pr)\n- owner/repo/pull/2\n<!-- /RELATED_PR -->\n"));
+
+        config.markers.empty_placeholder = String::new();
+        let body = make_body(&config, &fields, &BodyOptions::default());
+        assert!(body.starts_with("Related PRs:\n<!-- RELATED_PR -->\n<!-- /RELATED_PR -->\n"));
+    }
+
+    #[test]
+    fn test_make_body_tracking_line_positions() {
+        let fields = HashMap::from([("description".to_string(), "Adds things.".to_string())]);
+
+        let mut config = jira_config(TrackingLinePosition::Prepend);
+        config.jira.tracking_line_template = ":ticket: {{tag}}".to_string();
+        assert_eq!(make_body(&config, &fields, &tracked()), ":ticket: TRACK-1\n\n## This PR...\n\nAdds things.\n");
+
+        config.jira.tracking_line_position = TrackingLinePosition::Append;
+        assert_eq!(make_body(&config, &fields, &tracked()), "## This PR...\n\nAdds things.\n\n:ticket: TRACK-1\n");
+
+        config.jira.tracking_line_template = String::new();
+        assert_eq!(make_body(&config, &fields, &tracked()), "## This PR...\n\nAdds things.\n");
+    }
+
+    #[test]
+    fn test_placeholder_names() {
+        assert_eq!(placeholder_names("{{a}} text {{b_c}} {{ spaced }} {{a}}"), vec!["a".to_string(), "b_c".to_string(), "a".to_string()]);
+    }
+
+    #[test]
+    fn test_extract_fields() {
+        let config = Config::default();
+        let fields = HashMap::from([
+            ("description".to_string(), "Adds things.\n\nMore lines.".to_string()),
+            ("implementation".to_string(), "Carefully.".to_string()),
+        ]);
+        let body = make_body(&config, &fields, &BodyOptions::default());
+
+        let names = vec!["description".to_string(), "implementation".to_string(), "missing".to_string()];
+        let extracted = extract_fields(&config.template.body, &body, &names);
+
+        assert_eq!(extracted.get("description"), fields.get("description"));
+        assert_eq!(extracted.get("implementation"), fields.get("implementation"));
+        assert_eq!(extracted.get("missing"), None);
+    }
+
+    #[test]
+    fn test_carry_related_prs() {
+        let config = Config::default();
+        let old = replace_related_prs(&config, TEMPLATE, &1, &[pull_request(1), pull_request(2)]);
+
+        let result = carry_related_prs(&config, &old, TEMPLATE);
+
+        assert_eq!(result, old);
+    }
+
+    #[test]
+    fn test_replace_related_prs() {
+        let config = Config::default();
+        let body = TEMPLATE.to_string();
+
+        let result = replace_related_prs(&config, &body, &1, &[pull_request(1), pull_request(2)]);
+
+        assert!(result.contains("<!-- RELATED_PR -->\n- owner/repo/pull/1 - (this pr)\n- owner/repo/pull/2\n<!-- /RELATED_PR -->"));
+        assert!(!result.contains("<details>"));
+    }
+
+    #[test]
+    fn test_replace_related_prs_collapsible() {
+        let mut config = Config::default();
+        config.markers.collapsible = true;
+        let body = TEMPLATE.to_string();
+
+        let result = replace_related_prs(&config, &body, &1, &[pull_request(1), pull_request(2)]);
+        assert!(result.contains("<details><summary>Related PRs (2)</summary>\n\n<!-- RELATED_PR -->\n- owner/repo/pull/1 - (this pr)\n- owner/repo/pull/2\n<!-- /RELATED_PR -->\n</details>"));
+
+        let result = replace_related_prs(&config, &result, &1, &[pull_request(1), pull_request(2), pull_request(3)]);
+        assert!(result.contains("<details><summary>Related PRs (3)</summary>\n\n<!-- RELATED_PR -->\n- owner/repo/pull/1 - (this pr)\n- owner/repo/pull/2\n- owner/repo/pull/3\n<!-- /RELATED_PR -->\n</details>"));
+        assert_eq!(result.matches("<details>").count(), 1);
+        assert_eq!(result.matches("</details>").count(), 1);
+    }
+
+    #[test]
+    fn test_replace_related_prs_preserves_notes() {
+        let config = Config::default();
+        let body = "<!-- RELATED_PR -->\n- owner/repo/pull/1 - (this pr)\n- owner/repo/pull/7\nblocked by infra\n<!-- /RELATED_PR -->\n".to_string();
+
+        let result = replace_related_prs(&config, &body, &1, &[pull_request(1), pull_request(2)]);
+
+        assert_eq!(result, "<!-- RELATED_PR -->\n- owner/repo/pull/1 - (this pr)\n- owner/repo/pull/2\nblocked by infra\n<!-- /RELATED_PR -->\n");
+    }
+
+    #[test]
+    fn test_replace_related_prs_item_format() {
+        let mut config = Config::default();
+        config.template.related_pr_item = "- [#{{number}} {{title}}](https://github.com/{{path}}){{this_pr}}".to_string();
+        let body = "<!-- RELATED_PR -->\n- owner/repo/pull/1 - (this pr)\nblocked by infra\n<!-- /RELATED_PR -->\n";
+
+        let result = replace_related_prs(&config, body, &1, &[pull_request(1), pull_request(2)]);
+        assert_eq!(result, "<!-- RELATED_PR -->\n\
+            - [#1 [TRACK-1]: PR 1](https://github.com/owner/repo/pull/1) - (this pr)\n\
+            - [#2 [TRACK-1]: PR 2](https://github.com/owner/repo/pull/2)\n\
+            blocked by infra\n<!-- /RELATED_PR -->\n");
+
+        // Entries rendered with the format are regenerated, not kept as notes.
+        assert_eq!(replace_related_prs(&config, &result, &1, &[pull_request(1), pull_request(2)]), result);
+
+        config.template.related_pr_item = "- [!{{number}} {{title}}](https://gitlab.com/{{path}}){{this_pr}}".to_string();
+        let merge_requests = [1, 2].map(|number| PullRequest {
+            resource_path: format!("/group/project/-/merge_requests/{}", number),
+            ..pull_request(number)
+        });
+        let result = replace_related_prs(&config, TEMPLATE, &1, &merge_requests);
+        assert!(result.contains("<!-- RELATED_PR -->\n\
+            - [!1 [TRACK-1]: PR 1](https://gitlab.com/group/project/-/merge_requests/1) - (this pr)\n\
+            - [!2 [TRACK-1]: PR 2](https://gitlab.com/group/project/-/merge_requests/2)\n<!-- /RELATED_PR -->"));
+        assert_eq!(replace_related_prs(&config, &result, &1, &merge_requests), result);
+    }
+
+    #[test]
+    fn test_replace_related_prs_status() {
+        let config = Config::default();
+        let prs = [
+            PullRequest { state: PullRequestState::Merged, ..pull_request(1) },
+            PullRequest { is_draft: true, ..pull_request(2) },
+            PullRequest { state: PullRequestState::Closed, ..pull_request(3) },
+            pull_request(4),
+        ];
+
+        let result = replace_related_prs(&config, TEMPLATE, &2, &prs);
+        assert!(result.contains("- owner/repo/pull/1 (merged)\n- owner/repo/pull/2 (draft) - (this pr)\n- owner/repo/pull/3 (closed)\n- owner/repo/pull/4\n"));
+
+        // Entries of PRs merged since are regenerated.
+        let merged = [prs[0].clone(), PullRequest { is_draft: false, state: PullRequestState::Merged, ..prs[1].clone() }];
+        let result = replace_related_prs(&config, &result, &2, &merged);
+        assert!(result.contains("<!-- RELATED_PR -->\n- owner/repo/pull/1 (merged)\n- owner/repo/pull/2 (merged) - (this pr)\n<!-- /RELATED_PR -->"));
+    }
+
+    fn stacked(number: u32, base: &str, head: &str) -> PullRequest {
+        PullRequest {
+            base_ref_name: base.to_string(),
+            head_ref_name: head.to_string(),
+            ..pull_request(number)
         }
     }
-    related_prs_body.push("<!-- /RELATED_PR -->".into());
 
-    let re = Regex::new(r"(?sm)^<!-- RELATED_PR -->(.*)<!-- /RELATED_PR -->").unwrap();
-    let result = re.replace_all(body.as_str(), related_prs_body.join("\n"));
+    #[test]
+    fn test_replace_related_prs_stack() {
+        let config = Config::default();
+        let prs = [stacked(3, "feature-a", "feature-b"), stacked(5, "main", "feature-a"), stacked(4, "feature-b", "feature-c")];
+
+        let result = replace_related_prs(&config, TEMPLATE, &3, &prs);
+        assert!(result.contains("<!-- RELATED_PR -->\n- owner/repo/pull/5\n  - owner/repo/pull/3 - (this pr)\n    - owner/repo/pull/4\n<!-- /RELATED_PR -->"));
+        assert_eq!(replace_related_prs(&config, &result, &3, &prs), result);
+
+        // Two PRs on the same base aren't a chain.
+        let forked = [stacked(3, "main", "feature-b"), stacked(5, "main", "feature-a")];
+        let result = replace_related_prs(&config, TEMPLATE, &3, &forked);
+        assert!(result.contains("<!-- RELATED_PR -->\n- owner/repo/pull/3 - (this pr)\n- owner/repo/pull/5\n<!-- /RELATED_PR -->"));
+    }
+
+    #[test]
+    fn test_replace_related_prs_crlf() {
+        let config = Config::default();
+        let body = "Related PRs:\r\n<!-- RELATED_PR -->\r\n- owner/repo/pull/1 - (this pr)\r\nblocked by infra\r\n<!-- /RELATED_PR -->\r\n";
+
+        let result = replace_related_prs(&config, body, &1, &[pull_request(1), pull_request(2)]);
+        assert_eq!(result, "Related PRs:\r\n<!-- RELATED_PR -->\r\n- owner/repo/pull/1 - (this pr)\r\n- owner/repo/pull/2\r\nblocked by infra\r\n<!-- /RELATED_PR -->\r\n");
+        assert_eq!(replace_related_prs(&config, &result, &1, &[pull_request(1), pull_request(2)]), result);
+    }
+
+    #[test]
+    fn test_make_body_crlf() {
+        let mut config = jira_config(TrackingLinePosition::Marker);
+        config.template.body = config.template.body.replace('\n', "\r\n");
+        let fields = HashMap::from([("description".to_string(), "Adds things.\r\nAnd more.".to_string())]);
 
-    return result.to_string();
+        let body = make_body(&config, &fields, &tracked());
+        assert_eq!(body, "Tracked by [TRACK-1](https://example.atlassian.net/browse/TRACK-1)\n## This PR...\n\nAdds things.\nAnd more.\n");
+
+        let names = vec!["description".to_string()];
+        let extracted = extract_fields(&config.template.body, &body.replace('\n', "\r\n"), &names);
+        assert_eq!(extracted["description"], "Adds things.\nAnd more.");
+    }
+
+    #[test]
+    fn test_replace_related_prs_sorted() {
+        let config = Config::default();
+        let body = TEMPLATE.to_string();
+
+        let ordered = replace_related_prs(&config, &body, &2, &[pull_request(1), pull_request(2), pull_request(3)]);
+        let shuffled = replace_related_prs(&config, &body, &2, &[pull_request(3), pull_request(1), pull_request(2)]);
+
+        assert_eq!(ordered, shuffled);
+        assert!(ordered.contains("- owner/repo/pull/1\n- owner/repo/pull/2 - (this pr)\n- owner/repo/pull/3\n"));
+    }
 }
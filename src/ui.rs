@@ -0,0 +1,362 @@
+use std::collections::HashMap;
+use std::ffi::OsStr;
+use std::io::IsTerminal;
+
+use colored::Colorize;
+use inquire::{Confirm, CustomUserError, Editor, InquireError, MultiSelect, Text};
+use inquire::list_option::ListOption;
+use inquire::validator::Validation;
+
+use crate::config::{EditorFallback, EditorMode, FieldType, FormField, GitHubConfig, TagsConfig, TemplateConfig};
+use crate::error::{map_inquire_error, Error, Result};
+use crate::git::{BaseCandidates, BranchInfo};
+use crate::github::Reviewer;
+use crate::tags::tags::Tags;
+use crate::template;
+
+pub(crate) fn print_value(label: &str, value: &str) {
+    println!("{} {}: {}", ">".bright_green(), label, value.bright_cyan());
+}
+
+pub(crate) fn copy_to_clipboard(value: &str) {
+    let copied = arboard::Clipboard::new().and_then(|mut clipboard| clipboard.set_text(value));
+    match copied {
+        Ok(_) => println!("{} Copied {} to clipboard", ">".bright_green(), value.bright_cyan()),
+        Err(err) => println!("{} Unable to copy to clipboard ({}): {}", "!".bright_yellow(), err, value),
+    }
+}
+
+/// Prompts are drawn on stderr and read stdin, without a terminal they'd hang or fail cryptically.
+/// `flags` are the flags answering the prompt instead, if any.
+fn require_tty(prompt: &str, flags: &str) -> Result<()> {
+    if std::io::stdin().is_terminal() && std::io::stderr().is_terminal() {
+        return Ok(());
+    }
+
+    let mut message = format!("interactive prompt for {} required but no TTY", prompt);
+    if !flags.is_empty() {
+        message.push_str(&format!(", provide {}", flags));
+    }
+    Err(Error::InvalidInput(message))
+}
+
+/// Source of every interactive answer of the PR flow.
+pub trait Prompter {
+    /// Title of the PR, `commits` are the branch commit messages, oldest last.
+    fn title(&self, commits: &[String]) -> Result<String>;
+    /// Tag of the PR matching `config.pattern`, `history` are recently used tags, best ranked by `tags.ranking` first.
+    fn tag(&self, config: &TagsConfig, history: &[String]) -> Result<String>;
+    /// Base of the PR, any branch of `candidates` is accepted, not only the detected ones.
+    fn base(&self, candidates: BaseCandidates) -> Result<String>;
+    fn field(&self, template: &TemplateConfig, field: &FormField, placeholders: &HashMap<String, String>) -> Result<String>;
+    /// Logins of the picked `reviewers`.
+    fn reviewers(&self, config: &GitHubConfig, reviewers: Vec<Reviewer>) -> Result<Vec<String>>;
+    /// Typed logins of reviewers, asked when they couldn't be listed.
+    fn reviewer_logins(&self, config: &GitHubConfig) -> Result<Vec<String>>;
+    fn confirm(&self, message: &str, default: bool) -> Result<bool>;
+}
+
+/// [`Prompter`] asking the user in the terminal.
+pub struct InquirePrompter;
+
+impl Prompter for InquirePrompter {
+    fn title(&self, commits: &[String]) -> Result<String> {
+        require_tty("the PR title", "a commit titled `[TRACK-123]: ...` without --tag")?;
+        prompt_title(commits)
+    }
+
+    fn tag(&self, config: &TagsConfig, history: &[String]) -> Result<String> {
+        require_tty("the PR tag", "--tag")?;
+        prompt_tag(config, &Tags::from_vec(history.to_vec()))
+    }
+
+    fn base(&self, candidates: BaseCandidates) -> Result<String> {
+        require_tty("the PR base", "--base")?;
+        prompt_base(candidates)
+    }
+
+    fn field(&self, template: &TemplateConfig, field: &FormField, placeholders: &HashMap<String, String>) -> Result<String> {
+        require_tty(&format!("field {}", field.name), &format!("--field-file {}=PATH", field.name))?;
+        prompt_field(template, field, placeholders)
+    }
+
+    fn reviewers(&self, config: &GitHubConfig, reviewers: Vec<Reviewer>) -> Result<Vec<String>> {
+        require_tty("reviewers", "--reviewer or github.auto_reviewers")?;
+        prompt_reviewers(config, reviewers)
+    }
+
+    fn reviewer_logins(&self, config: &GitHubConfig) -> Result<Vec<String>> {
+        require_tty("reviewers", "--reviewer or github.auto_reviewers")?;
+        prompt_reviewer_logins(config)
+    }
+
+    fn confirm(&self, message: &str, default: bool) -> Result<bool> {
+        require_tty(&format!("`{}`", message), "")?;
+        Confirm::new(message)
+            .with_default(default)
+            .prompt()
+            .map_err(map_inquire_error)
+    }
+}
+
+fn prompt_title(commits: &[String]) -> Result<String> {
+    let default = commits.last().cloned().unwrap_or_default();
+
+    Text::new("PR title: ")
+        .with_default(&default)
+        .with_autocomplete(BranchInfo {
+            commits: commits.to_vec(),
+            ..Default::default()
+        })
+        .prompt()
+        .map_err(map_inquire_error)
+}
+
+/// History only suggests tags, any tag matching `tags.pattern` is accepted.
+fn prompt_tag(config: &TagsConfig, tags: &Tags) -> Result<String> {
+    let default = tags.iter().first().cloned().unwrap_or_default();
+    let validated = config.clone();
+
+    let mut text = Text::new("PR Tag:")
+        .with_validator(move |input: &str| -> std::result::Result<Validation, CustomUserError> {
+            Ok(validate_tag(&validated, input))
+        });
+    if !tags.is_empty() {
+        text = text
+            .with_autocomplete(tags.clone())
+            .with_default(&default);
+    }
+
+    text.prompt()
+        .map(|tag| tag.trim().to_string())
+        .map_err(map_inquire_error)
+}
+
+/// Same check as for `--tag`, surrounding whitespace is trimmed from the answer.
+fn validate_tag(config: &TagsConfig, input: &str) -> Validation {
+    match config.validate(input.trim()) {
+        Ok(_) => Validation::Valid,
+        Err(msg) => Validation::Invalid(msg.into()),
+    }
+}
+
+fn prompt_base(candidates: BaseCandidates) -> Result<String> {
+    let default = candidates.detected.first().cloned().unwrap_or_default();
+    let validated = candidates.clone();
+
+    Text::new("PR base:")
+        .with_default(&default)
+        .with_help_message("pick a detected base or type any branch name")
+        .with_autocomplete(candidates)
+        .with_validator(move |input: &str| -> std::result::Result<Validation, CustomUserError> {
+            if validated.is_known(input) {
+                return Ok(Validation::Valid);
+            }
+            Ok(Validation::Invalid(format!("{} is not a local or remote branch", input).into()))
+        })
+        .prompt()
+        .map_err(map_inquire_error)
+}
+
+fn prompt_field(template: &TemplateConfig, field: &FormField, placeholders: &HashMap<String, String>) -> Result<String> {
+    let default = field.default.as_deref().map(|d| template::expand_placeholders(d, placeholders));
+
+    match field.field_type {
+        FieldType::Text => {
+            let validated = field.clone();
+            let mut text = Text::new(&field.message)
+                .with_validator(move |input: &str| -> std::result::Result<Validation, CustomUserError> {
+                    match validated.validate(input) {
+                        Ok(_) => Ok(Validation::Valid),
+                        Err(msg) => Ok(Validation::Invalid(msg.into())),
+                    }
+                });
+            if let Some(default) = &default {
+                text = text.with_default(default);
+            }
+
+            text.prompt().map_err(map_inquire_error)
+        }
+        FieldType::Editor => loop {
+            let value = prompt_editor_field(template, field, default.as_deref())?;
+            match field.validate(&value) {
+                Ok(_) => return Ok(value),
+                Err(msg) => println!("{} {}", "x".bright_red(), msg),
+            }
+        },
+    }
+}
+
+fn prompt_editor_field(template: &TemplateConfig, field: &FormField, default: Option<&str>) -> Result<String> {
+    let message = field.message.as_str();
+    if template.editor_mode == EditorMode::Inline {
+        return prompt_inline_field(message, default);
+    }
+
+    let command = template.editor_command();
+    let args: Vec<&OsStr> = command.iter().flat_map(|(_, args)| args.iter().map(OsStr::new)).collect();
+
+    let mut editor = Editor::new(message)
+        .with_formatter(&|x| -> String { x.to_string() });
+    if let Some(default) = default {
+        editor = editor.with_predefined_text(default);
+    }
+    if let Some((program, _)) = &command {
+        editor = editor
+            .with_editor_command(OsStr::new(program))
+            .with_args(&args);
+    }
+
+    match editor.prompt() {
+        Err(InquireError::IO(err)) => editor_fallback(template, field, default, err),
+        result => result.map_err(map_inquire_error),
+    }
+}
+
+/// Value of `field` when the editor failed with `err`, following `template.editor_fallback`.
+fn editor_fallback(template: &TemplateConfig, field: &FormField, default: Option<&str>, err: std::io::Error) -> Result<String> {
+    match (template.editor_fallback, default) {
+        (EditorFallback::Inline, _) => {
+            println!("{} Editor failed: {}, type the value instead", "!".bright_yellow(), err);
+            prompt_inline_field(&field.message, default)
+        }
+        (EditorFallback::Default, Some(default)) if field.validate(default).is_ok() => {
+            println!("{} Editor failed: {}, using the default", "!".bright_yellow(), err);
+            Ok(default.to_string())
+        }
+        _ => Err(map_inquire_error(InquireError::IO(err))),
+    }
+}
+
+/// Lines typed until an empty one, `default` is shown and kept when nothing is typed.
+fn prompt_inline_field(message: &str, default: Option<&str>) -> Result<String> {
+    let default = default.filter(|default| !default.trim().is_empty());
+    match default {
+        Some(default) => {
+            println!("{} {} {}", ">".bright_green(), message, "(empty line to finish, nothing typed keeps the default)".bright_black());
+            for line in default.lines() {
+                println!("  {}", line.bright_black());
+            }
+        }
+        None => println!("{} {} {}", ">".bright_green(), message, "(empty line to finish)".bright_black()),
+    }
+
+    let mut lines: Vec<String> = Vec::new();
+    loop {
+        let line = Text::new("")
+            .with_formatter(&|x| -> String { x.to_string() })
+            .prompt()
+            .map_err(map_inquire_error)?;
+        if line.is_empty() {
+            break;
+        }
+        lines.push(line);
+    }
+
+    Ok(inline_value(&lines, default))
+}
+
+fn inline_value(lines: &[String], default: Option<&str>) -> String {
+    match default {
+        Some(default) if lines.is_empty() => default.to_string(),
+        _ => lines.join("\n"),
+    }
+}
+
+/// Case-insensitive substring match of the name or login.
+fn filter_reviewer(filter: &str, _reviewer: &Reviewer, string_value: &str, _index: usize) -> bool {
+    string_value.to_lowercase().contains(filter.to_lowercase().as_str())
+}
+
+/// Logins separated by commas or whitespace.
+fn prompt_reviewer_logins(config: &GitHubConfig) -> Result<Vec<String>> {
+    let split = |input: &str| -> Vec<String> {
+        input.split(|c: char| c == ',' || c.is_whitespace())
+            .filter(|login| !login.is_empty())
+            .map(str::to_string)
+            .collect()
+    };
+    let require_reviewer = config.require_reviewer;
+
+    let input = Text::new("Reviewers:")
+        .with_default(&config.default_reviewers.join(", "))
+        .with_help_message("logins or org/team slugs, separated by commas")
+        .with_validator(move |input: &str| -> std::result::Result<Validation, CustomUserError> {
+            if require_reviewer && split(input).is_empty() {
+                return Ok(Validation::Invalid("Type at least one reviewer".into()));
+            }
+            Ok(Validation::Valid)
+        })
+        .prompt()
+        .map_err(map_inquire_error)?;
+
+    Ok(split(&input))
+}
+
+fn prompt_reviewers(config: &GitHubConfig, reviewers: Vec<Reviewer>) -> Result<Vec<String>> {
+    let defaults: Vec<usize> = reviewers.iter()
+        .enumerate()
+        .filter(|(_, reviewer)| config.default_reviewers.contains(&reviewer.login))
+        .map(|(i, _)| i)
+        .collect();
+    let require_reviewer = config.require_reviewer;
+
+    MultiSelect::new("Reviewers:", reviewers)
+        .with_default(&defaults)
+        .with_page_size(config.reviewer_page_size)
+        .with_filter(&filter_reviewer)
+        .with_validator(move |a: &[ListOption<&Reviewer>]| -> std::result::Result<Validation, CustomUserError> {
+            if require_reviewer && a.is_empty() {
+                return Ok(Validation::Invalid("Select at least one reviewer".into()));
+            }
+            Ok(Validation::Valid)
+        })
+        .with_formatter(&|a| -> String {
+            let selected: Vec<String> = a.iter().map(|x| -> String{ x.value.login.clone() }).collect();
+            selected.join(", ")
+        })
+        .prompt()
+        .map(|picked| picked.into_iter().map(|reviewer| reviewer.login).collect())
+        .map_err(map_inquire_error)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_editor_fallback() {
+        let failed = || std::io::Error::new(std::io::ErrorKind::NotFound, "vi not found");
+        let field = &FormField { name: "description".to_string(), field_type: FieldType::Editor, required: true, min_length: Some(10), ..Default::default() };
+        let template = TemplateConfig { editor_fallback: EditorFallback::Default, ..Default::default() };
+
+        assert_eq!(editor_fallback(&template, field, Some("Adds the things"), failed()).unwrap(), "Adds the things");
+        assert!(editor_fallback(&template, field, Some("Too short"), failed()).is_err());
+        assert!(editor_fallback(&template, field, None, failed()).is_err());
+
+        let template = TemplateConfig { editor_fallback: EditorFallback::Error, ..Default::default() };
+        assert!(editor_fallback(&template, field, Some("Adds the things"), failed()).is_err());
+    }
+
+    #[test]
+    fn test_validate_tag() {
+        let config = TagsConfig::default();
+        assert!(matches!(validate_tag(&config, " TRACK-1 "), Validation::Valid));
+        for malformed in ["", "TRACK-", "TRACK 123", "[TRACK-123]", "TRACK--123"] {
+            assert!(matches!(validate_tag(&config, malformed), Validation::Invalid(_)), "{}", malformed);
+        }
+
+        let config = TagsConfig { pattern: r"^[A-Z]+-\d+$".to_string(), ..Default::default() };
+        assert!(matches!(validate_tag(&config, "TRACK-1"), Validation::Valid));
+        assert!(matches!(validate_tag(&config, "TRACK"), Validation::Invalid(_)));
+    }
+
+    #[test]
+    fn test_inline_value() {
+        let lines = vec!["Adds things.".to_string(), "And more.".to_string()];
+
+        assert_eq!(inline_value(&lines, Some("Default")), "Adds things.\nAnd more.");
+        assert_eq!(inline_value(&[], Some("Default")), "Default");
+        assert_eq!(inline_value(&[], None), "");
+    }
+}